@@ -0,0 +1,84 @@
+#![feature(test)]
+
+extern crate test;
+
+use futures_lite::future::block_on;
+use futures_util::{
+    io::{AsyncReadExt, Cursor},
+    stream::TryStreamExt,
+};
+use yz_futures_codec::{
+    codec::{Decoder, Encoder, Length},
+    BytesMut, Framed, ReadStrategy,
+};
+
+fn frames(count: usize, size: usize) -> Vec<u8> {
+    let mut codec = Length::<u32>::new();
+    let mut buf = BytesMut::new();
+    let payload = vec![0x55u8; size];
+    for _ in 0..count {
+        codec.encode(&payload[..], &mut buf).unwrap();
+    }
+    buf.to_vec()
+}
+
+fn run_framed(data: &[u8], count: usize, strategy: ReadStrategy) {
+    block_on(async {
+        let mut framed = Framed::new(Cursor::new(test::black_box(data)), Length::<u32>::new());
+        framed.r_strategy = strategy;
+        for _ in 0..count {
+            framed.try_next().await.unwrap().unwrap();
+        }
+    })
+}
+
+/// the previous read path: zero a stack buffer on every `poll_next`,
+/// then copy the read data into the read buffer
+fn run_stack_copy(data: &[u8], count: usize) {
+    block_on(async {
+        let mut read = Cursor::new(test::black_box(data));
+        let mut codec = Length::<u32>::new();
+        let mut r_buffer = BytesMut::with_capacity(8 * 1024);
+        for _ in 0..count {
+            let mut buf = test::black_box([0u8; 8 * 1024]);
+            while codec.decode(&mut r_buffer).unwrap().is_none() {
+                let n = read.read(&mut buf).await.unwrap();
+                r_buffer.extend_from_slice(&buf[..n]);
+            }
+        }
+    })
+}
+
+#[bench]
+fn small_frames_stack_copy(b: &mut test::Bencher) {
+    let data = frames(1024, 16);
+    b.iter(|| run_stack_copy(&data, 1024))
+}
+
+#[bench]
+fn small_frames_exact(b: &mut test::Bencher) {
+    let data = frames(1024, 16);
+    b.iter(|| run_framed(&data, 1024, ReadStrategy::default()))
+}
+
+#[bench]
+fn large_frames_stack_copy(b: &mut test::Bencher) {
+    let data = frames(16, 256 * 1024);
+    b.iter(|| run_stack_copy(&data, 16))
+}
+
+#[bench]
+fn large_frames_exact(b: &mut test::Bencher) {
+    let data = frames(16, 256 * 1024);
+    b.iter(|| run_framed(&data, 16, ReadStrategy::default()))
+}
+
+#[bench]
+fn large_frames_adaptive(b: &mut test::Bencher) {
+    let data = frames(16, 256 * 1024);
+    let strategy = ReadStrategy::Adaptive {
+        initial: 8 * 1024,
+        max: 1024 * 1024,
+    };
+    b.iter(|| run_framed(&data, 16, strategy))
+}
@@ -0,0 +1,222 @@
+use crate::codec::{Decoder, Encoder};
+use crate::read::{next_frame_with, ReadBuffer, INITIAL_CAPACITY};
+use crate::stats::StatsCell;
+use crate::write::DEFAULT_HIGH_WATER_MARK;
+use crate::{Error, ReadState, ReadStrategy, WriteBuffer};
+use bytes::BytesMut;
+use std::io::{self, Read, Write};
+use std::iter::FusedIterator;
+use std::ops::Deref;
+use std::task::Poll;
+
+/// unwraps the result of a poll function which never returns `Pending`
+fn ready<T>(poll: Poll<T>) -> T {
+    match poll {
+        Poll::Ready(x) => x,
+        Poll::Pending => unreachable!("blocking I/O returned Pending"),
+    }
+}
+
+/// A blocking counterpart of [`Framed`](crate::Framed), for use with
+/// [`std::io::Read`] and [`std::io::Write`].
+///
+/// Frames are decoded via the [`Iterator`] implementation,
+/// which follows the semantics of the `Stream` part of `Framed`,
+/// and encoded via [`send`](FramedSync::send) and [`feed`](FramedSync::feed).
+///
+/// # Example
+/// ```
+/// use std::io::Cursor;
+/// use yz_futures_codec::{codec::Lines, FramedSync, Error};
+///
+/// let mut framed = FramedSync::new(Cursor::new(Vec::new()), Lines);
+/// framed.send("hello\n").unwrap();
+/// framed.inner_mut().set_position(0);
+/// assert_eq!(framed.next().transpose()?, Some("hello\n".to_string()));
+/// assert_eq!(framed.next().transpose()?, None);
+/// # Ok::<_, Error<_>>(())
+/// ```
+#[derive(Debug)]
+pub struct FramedSync<T, U> {
+    inner: T,
+
+    /// the codec used to encode and decode frames
+    pub codec: U,
+
+    // write
+    w_buffer: WriteBuffer,
+    /// The high-water mark for writes, in bytes
+    ///
+    /// see [`Framed::w_high_water_mark`](crate::Framed::w_high_water_mark)
+    pub w_high_water_mark: usize,
+
+    // read
+    r_buffer: ReadBuffer,
+    /// The strategy used to size reads from the underlying I/O object
+    ///
+    /// see [`Framed::r_strategy`](crate::Framed::r_strategy)
+    pub r_strategy: ReadStrategy,
+    /// The maximum size of the read buffer, in bytes
+    ///
+    /// see [`Framed::r_max_buffer_size`](crate::Framed::r_max_buffer_size)
+    pub r_max_buffer_size: Option<usize>,
+    /// Whether codec errors end the iterator
+    ///
+    /// see [`Framed::r_end_on_error`](crate::Framed::r_end_on_error)
+    pub r_end_on_error: bool,
+
+    stats: StatsCell,
+}
+
+impl<T, U> Deref for FramedSync<T, U> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T, U> FramedSync<T, U> {
+    /// Creates a new `FramedSync` transport with the given codec.
+    pub fn new(inner: T, codec: U) -> Self {
+        Self {
+            inner,
+            codec,
+            w_buffer: WriteBuffer::with_capacity(INITIAL_CAPACITY),
+            w_high_water_mark: DEFAULT_HIGH_WATER_MARK,
+            r_buffer: ReadBuffer::new(),
+            r_strategy: ReadStrategy::default(),
+            r_max_buffer_size: None,
+            r_end_on_error: false,
+            stats: StatsCell::default(),
+        }
+    }
+
+    /// Release the I/O and Codec
+    ///
+    /// Note that any data which wasn't flushed yet, and any data
+    /// which was read but not decoded yet is lost.
+    pub fn release(self) -> (T, U) {
+        (self.inner, self.codec)
+    }
+
+    /// Consumes the `FramedSync`, returning its underlying I/O stream.
+    ///
+    /// Note that any data which wasn't flushed yet, and any data
+    /// which was read but not decoded yet is lost.
+    pub fn into_inner(self) -> T {
+        self.release().0
+    }
+
+    /// Returns a mutable reference to the underlying I/O stream.
+    ///
+    /// Note that care should be taken to not tamper with the underlying stream
+    /// of data coming in as it may corrupt the stream of frames otherwise
+    /// being worked with.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Returns a reference to the read buffer.
+    pub fn read_buffer(&self) -> &BytesMut {
+        &self.r_buffer.data
+    }
+
+    /// Returns the state of the reading part.
+    pub fn read_state(&self) -> ReadState {
+        self.r_buffer.state
+    }
+
+    /// Returns a reference to the write buffer.
+    pub fn write_buffer(&self) -> &WriteBuffer {
+        &self.w_buffer
+    }
+
+    /// Returns the traffic statistics of this `FramedSync`.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> &crate::Stats {
+        self.stats.get()
+    }
+}
+
+impl<T: Write, U> FramedSync<T, U> {
+    /// writes out the write buffer until at most `limit` bytes remain in it
+    fn flush_until(&mut self, limit: usize) -> io::Result<()> {
+        let orig_len = self.w_buffer.len();
+
+        while self.w_buffer.len() > limit {
+            let inner = &mut self.inner;
+            match ready(self.w_buffer.write_with(&mut self.stats, |slices| {
+                Poll::Ready(inner.write_vectored(slices))
+            })) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                x => x?,
+            }
+        }
+
+        if orig_len != self.w_buffer.len() {
+            self.inner.flush()?;
+            self.stats.on_flush();
+        }
+        Ok(())
+    }
+
+    /// Encodes the given item into the write buffer, without flushing it.
+    ///
+    /// If the write buffer exceeds the high-water mark,
+    /// it is written out before the item is encoded.
+    pub fn feed<Item>(&mut self, item: &Item) -> Result<(), Error<U::Error>>
+    where
+        Item: ?Sized,
+        U: Encoder<Item>,
+    {
+        self.flush_until(self.w_high_water_mark - 1)?;
+        self.codec
+            .encode_vectored(item, &mut self.w_buffer)
+            .map_err(Error::Codec)?;
+        self.stats.on_encode(self.w_buffer.len());
+        Ok(())
+    }
+
+    /// Encodes the given item and flushes the write buffer.
+    pub fn send<Item>(&mut self, item: &Item) -> Result<(), Error<U::Error>>
+    where
+        Item: ?Sized,
+        U: Encoder<Item>,
+    {
+        self.feed(item)?;
+        self.flush()?;
+        Ok(())
+    }
+
+    /// Writes out the whole write buffer, and flushes the underlying I/O object.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.flush_until(0)
+    }
+}
+
+impl<T: Read, U: Decoder> Iterator for FramedSync<T, U> {
+    type Item = Result<U::Item, Error<U::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let inner = &mut self.inner;
+        ready(next_frame_with(
+            &mut self.codec,
+            &mut self.r_buffer,
+            self.r_strategy,
+            self.r_max_buffer_size,
+            self.r_end_on_error,
+            &mut self.stats,
+            |buf| {
+                Poll::Ready(loop {
+                    match inner.read(buf) {
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                        x => break x,
+                    }
+                })
+            },
+        ))
+    }
+}
+
+impl<T: Read, U: Decoder> FusedIterator for FramedSync<T, U> {}
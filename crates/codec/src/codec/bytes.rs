@@ -1,9 +1,12 @@
-use super::{Decoder, Encoder};
+use super::{Decoder, Encoder, Payload};
+use crate::WriteBuffer;
 use bytes::{Bytes, BytesMut};
 use std::convert::Infallible;
 
 /// A simple codec that ships bytes around
 ///
+/// Payloads sent as [`Bytes`] aren't copied into the write buffer.
+///
 /// # Example
 ///
 ///  ```
@@ -35,10 +38,18 @@ impl super::EncoderError for BytesCodec {
 
 impl<Item> Encoder<Item> for BytesCodec
 where
-    Item: AsRef<[u8]> + ?Sized,
+    Item: Payload + ?Sized,
 {
     fn encode(&mut self, src: &Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        dst.extend_from_slice(src.as_ref());
+        dst.extend_from_slice(src.as_slice());
+        Ok(())
+    }
+
+    fn encode_vectored(&mut self, src: &Item, dst: &mut WriteBuffer) -> Result<(), Self::Error> {
+        match src.as_shared() {
+            Some(src) => dst.push_bytes(src),
+            None => dst.extend_from_slice(src.as_slice()),
+        }
         Ok(())
     }
 }
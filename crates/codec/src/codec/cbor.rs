@@ -1,6 +1,6 @@
-use super::{Decoder, Encoder, EncoderError};
-use bytes::{Buf, BufMut, BytesMut};
-use serde::{Deserialize, Serialize};
+use super::{Decoder, Encoder};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
 use std::marker::PhantomData;
 
 /// A codec for JSON encoding and decoding using serde_cbor
@@ -41,15 +41,16 @@ where
 
     fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         // Build deserializer
-        let mut de = serde_cbor::Deserializer::from_slice(&buf);
+        let mut de = serde_cbor::Deserializer::from_slice(buf);
 
         // Attempt deserialization
         let res: Result<Dec, _> = serde::de::Deserialize::deserialize(&mut de);
 
         // If we ran out before parsing, return none and try again later
+        // (without discarding the partially parsed item)
         let res = match res {
             Ok(v) => Ok(Some(v)),
-            Err(e) if e.is_eof() => Ok(None),
+            Err(e) if e.is_eof() => return Ok(None),
             Err(e) => Err(e),
         };
 
@@ -63,6 +64,55 @@ where
     }
 }
 
+/// A single complete CBOR value, which owns its buffer.
+///
+/// Decoding into `CborFrame` (via `Cbor<Enc, CborFrame>`) doesn't deserialize
+/// the value, which allows deserializing it afterwards into types
+/// which borrow from the frame (e.g. `&str` or `&[u8]`),
+/// which avoids allocating each field separately.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CborFrame(Bytes);
+
+impl CborFrame {
+    /// Returns the serialized value.
+    pub fn bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    /// Consumes the frame, returning the serialized value.
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+
+    /// Deserializes the value, borrowing from the frame where possible.
+    pub fn deserialize<'de, T: Deserialize<'de>>(&'de self) -> Result<T, serde_cbor::Error> {
+        serde_cbor::from_slice(&self.0)
+    }
+}
+
+/// Decoder impl splits cbor values from bytes, without deserializing them
+impl<Enc> Decoder for Cbor<Enc, CborFrame> {
+    type Item = CborFrame;
+    type Error = serde_cbor::Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let mut de = serde_cbor::Deserializer::from_slice(buf);
+
+        match IgnoredAny::deserialize(&mut de) {
+            Ok(_) => {
+                let offset = de.byte_offset();
+                Ok(Some(CborFrame(buf.split_to(offset).freeze())))
+            }
+            Err(e) if e.is_eof() => Ok(None),
+            Err(e) => {
+                let offset = de.byte_offset();
+                buf.advance(offset);
+                Err(e)
+            }
+        }
+    }
+}
+
 impl<Enc, Dec> super::EncoderError for Cbor<Enc, Dec>
 where
     Enc: Serialize + 'static,
@@ -76,12 +126,13 @@ where
     Enc: Serialize + 'static,
 {
     fn encode(&mut self, data: &Enc, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        // Encode cbor
-        let j = serde_cbor::to_vec(data)?;
-
-        // Write to buffer
-        buf.reserve(j.len());
-        buf.put_slice(&j);
+        // Encode cbor directly into the buffer,
+        // but don't leave a partially encoded value behind
+        let orig_len = buf.len();
+        if let Err(e) = serde_cbor::to_writer((&mut *buf).writer(), data) {
+            buf.truncate(orig_len);
+            return Err(e);
+        }
 
         Ok(())
     }
@@ -92,8 +143,9 @@ mod test {
     use bytes::BytesMut;
     use serde::{Deserialize, Serialize};
 
-    use super::Cbor;
-    use crate::{Decoder, Encoder};
+    use super::{Cbor, CborFrame};
+    use crate::codec::test_util::{check_encode_error, Failing};
+    use crate::codec::{Decoder, Encoder};
 
     #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
     struct TestStruct {
@@ -110,7 +162,7 @@ mod test {
             name: "Test name".to_owned(),
             data: 16,
         };
-        codec.encode(item1.clone(), &mut buff).unwrap();
+        codec.encode(&item1, &mut buff).unwrap();
 
         let item2 = codec.decode(&mut buff).unwrap().unwrap();
         assert_eq!(item1, item2);
@@ -129,7 +181,7 @@ mod test {
             name: "Test name".to_owned(),
             data: 34,
         };
-        codec.encode(item1.clone(), &mut buff).unwrap();
+        codec.encode(&item1, &mut buff).unwrap();
 
         let mut start = buff.clone().split_to(4);
         assert_eq!(codec.decode(&mut start).unwrap(), None);
@@ -138,4 +190,57 @@ mod test {
 
         assert_eq!(buff.len(), 0);
     }
+
+    #[test]
+    fn cbor_codec_keeps_partial_item() {
+        let mut codec = Cbor::<TestStruct, TestStruct>::new();
+        let mut encoded = BytesMut::new();
+
+        let item1 = TestStruct {
+            name: "Test name".to_owned(),
+            data: 34,
+        };
+        codec.encode(&item1, &mut encoded).unwrap();
+
+        // the partially received bytes must stay in the buffer
+        let mut buff = encoded.split_to(4);
+        assert_eq!(codec.decode(&mut buff).unwrap(), None);
+        assert_eq!(buff.len(), 4);
+
+        buff.unsplit(encoded);
+        assert_eq!(codec.decode(&mut buff).unwrap(), Some(item1));
+        assert_eq!(buff.len(), 0);
+    }
+
+    #[test]
+    fn cbor_codec_borrowing_decode() {
+        use serde_cbor::Value;
+
+        let mut codec = Cbor::<Value, CborFrame>::new();
+        let mut buff = BytesMut::new();
+
+        let item1 = Value::Array(vec![
+            Value::Text("Test name".to_owned()),
+            Value::Bytes(vec![1, 2, 3]),
+        ]);
+        codec.encode(&item1, &mut buff).unwrap();
+        buff.extend_from_slice(&[0x82]);
+
+        let frame = codec.decode(&mut buff).unwrap().unwrap();
+        let (name, data): (&str, &[u8]) = frame.deserialize().unwrap();
+        assert_eq!(name, "Test name");
+        assert_eq!(data, &[1, 2, 3]);
+        assert_eq!(
+            data.as_ptr(),
+            frame.bytes()[frame.bytes().len() - 3..].as_ptr()
+        );
+
+        assert_eq!(codec.decode(&mut buff).unwrap(), None);
+        assert_eq!(&buff[..], &[0x82]);
+    }
+
+    #[test]
+    fn cbor_codec_encode_error() {
+        check_encode_error(Cbor::<Failing, ()>::new());
+    }
 }
@@ -0,0 +1,519 @@
+use super::{Decoder, DecoderWithSkipAhead, Encoder, EncoderError};
+use crate::WriteBuffer;
+use bytes::BytesMut;
+use std::{fmt, marker::PhantomData};
+
+/// Combinators for [`Decoder`]s.
+///
+/// All adapters pass the `Encoder` implementation of the wrapped codec through
+/// and implement [`DecoderWithSkipAhead`] if the wrapped codec does,
+/// so that they can be used with [`Limit`](super::Limit).
+///
+/// # Example
+///
+/// ```
+/// use yz_futures_codec::codec::{Decoder, DecoderExt, Encoder, EncoderExt, Length, Limit};
+/// use bytes::BytesMut;
+///
+/// let mut codec = Limit::new(
+///     Length::<u64>::new()
+///         .and_then(|bytes| String::from_utf8(bytes.to_vec()))
+///         .contramap(|s: &String| s.clone().into_bytes()),
+///     1024,
+/// );
+///
+/// let mut buf = BytesMut::new();
+/// codec.encode(&"hello".to_string(), &mut buf).unwrap();
+/// assert_eq!(codec.decode(&mut buf).unwrap(), Some("hello".to_string()));
+/// ```
+pub trait DecoderExt: Decoder + Sized {
+    /// Maps the decoded items using the given function.
+    fn map<F, T>(self, f: F) -> Map<Self, F>
+    where
+        F: FnMut(Self::Item) -> T,
+    {
+        Map { inner: self, f }
+    }
+
+    /// Maps the decoded items using the given fallible function.
+    fn and_then<F, T, E>(self, f: F) -> AndThen<Self, F>
+    where
+        F: FnMut(Self::Item) -> Result<T, E>,
+        E: std::error::Error + 'static,
+    {
+        AndThen { inner: self, f }
+    }
+
+    /// Maps the decoding errors using the given function.
+    fn map_err<F, E>(self, f: F) -> MapErr<Self, F>
+    where
+        F: FnMut(Self::Error) -> E,
+        E: std::error::Error + 'static,
+    {
+        MapErr { inner: self, f }
+    }
+}
+
+impl<C: Decoder> DecoderExt for C {}
+
+/// Combinators for [`Encoder`]s.
+///
+/// All adapters pass the `Decoder` (and [`DecoderWithSkipAhead`])
+/// implementation of the wrapped codec through.
+pub trait EncoderExt: EncoderError + Sized {
+    /// Converts the items to send using the given function,
+    /// before they are encoded by this encoder.
+    fn contramap<F, T, I>(self, f: F) -> Contramap<Self, F, I>
+    where
+        T: ?Sized,
+        F: FnMut(&T) -> I,
+        Self: Encoder<I>,
+    {
+        Contramap {
+            inner: self,
+            f,
+            _item: PhantomData,
+        }
+    }
+
+    /// Maps the encoding errors using the given function.
+    ///
+    /// This is named differently than [`DecoderExt::map_err`] (like `sink_map_err`
+    /// of `futures`), because most codecs are both a `Decoder` and an `Encoder`.
+    fn map_encode_err<F, E>(self, f: F) -> MapEncodeErr<Self, F>
+    where
+        F: FnMut(Self::Error) -> E,
+        E: std::error::Error + 'static,
+    {
+        MapEncodeErr { inner: self, f }
+    }
+}
+
+impl<C: EncoderError> EncoderExt for C {}
+
+/// Decoder adapter created by [`DecoderExt::map`].
+#[derive(Clone)]
+pub struct Map<C, F> {
+    inner: C,
+    f: F,
+}
+
+impl<C: fmt::Debug, F> fmt::Debug for Map<C, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Map").field("inner", &self.inner).finish()
+    }
+}
+
+impl<C, F> Map<C, F> {
+    /// Consumes the adapter, returning the wrapped codec.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C: EncoderError, F> EncoderError for Map<C, F> {
+    type Error = C::Error;
+}
+
+impl<Item, C, F> Encoder<Item> for Map<C, F>
+where
+    Item: ?Sized,
+    C: Encoder<Item>,
+{
+    fn encode(&mut self, item: &Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.inner.encode(item, dst)
+    }
+
+    fn encode_vectored(&mut self, item: &Item, dst: &mut WriteBuffer) -> Result<(), Self::Error> {
+        self.inner.encode_vectored(item, dst)
+    }
+}
+
+impl<C, F, T> Decoder for Map<C, F>
+where
+    C: Decoder,
+    F: FnMut(C::Item) -> T,
+{
+    type Item = T;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.inner.decode(src)?.map(&mut self.f))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.inner.decode_eof(src)?.map(&mut self.f))
+    }
+}
+
+impl<C, F, T> DecoderWithSkipAhead for Map<C, F>
+where
+    C: DecoderWithSkipAhead,
+    F: FnMut(C::Item) -> T,
+{
+    type Handler = C::Handler;
+
+    fn prepare_skip_ahead(&mut self, src: &mut BytesMut) -> Self::Handler {
+        self.inner.prepare_skip_ahead(src)
+    }
+}
+
+/// Decoder adapter created by [`DecoderExt::and_then`].
+#[derive(Clone)]
+pub struct AndThen<C, F> {
+    inner: C,
+    f: F,
+}
+
+impl<C: fmt::Debug, F> fmt::Debug for AndThen<C, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AndThen")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<C, F> AndThen<C, F> {
+    /// Consumes the adapter, returning the wrapped codec.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C: EncoderError, F> EncoderError for AndThen<C, F> {
+    type Error = C::Error;
+}
+
+impl<Item, C, F> Encoder<Item> for AndThen<C, F>
+where
+    Item: ?Sized,
+    C: Encoder<Item>,
+{
+    fn encode(&mut self, item: &Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.inner.encode(item, dst)
+    }
+
+    fn encode_vectored(&mut self, item: &Item, dst: &mut WriteBuffer) -> Result<(), Self::Error> {
+        self.inner.encode_vectored(item, dst)
+    }
+}
+
+/// The error type used by [`AndThen`].
+#[derive(Debug, thiserror::Error)]
+pub enum AndThenError<C: std::error::Error + 'static, E: std::error::Error + 'static> {
+    /// the wrapped decoder failed
+    #[error(transparent)]
+    Inner(C),
+
+    /// the mapping function failed
+    #[error(transparent)]
+    Map(E),
+}
+
+impl<C, F, T, E> Decoder for AndThen<C, F>
+where
+    C: Decoder,
+    F: FnMut(C::Item) -> Result<T, E>,
+    E: std::error::Error + 'static,
+{
+    type Item = T;
+    type Error = AndThenError<C::Error, E>;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.inner.decode(src).map_err(AndThenError::Inner)? {
+            Some(item) => (self.f)(item).map(Some).map_err(AndThenError::Map),
+            None => Ok(None),
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.inner.decode_eof(src).map_err(AndThenError::Inner)? {
+            Some(item) => (self.f)(item).map(Some).map_err(AndThenError::Map),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<C, F, T, E> DecoderWithSkipAhead for AndThen<C, F>
+where
+    C: DecoderWithSkipAhead,
+    F: FnMut(C::Item) -> Result<T, E>,
+    E: std::error::Error + 'static,
+{
+    type Handler = C::Handler;
+
+    fn prepare_skip_ahead(&mut self, src: &mut BytesMut) -> Self::Handler {
+        self.inner.prepare_skip_ahead(src)
+    }
+}
+
+/// Decoder adapter created by [`DecoderExt::map_err`].
+#[derive(Clone)]
+pub struct MapErr<C, F> {
+    inner: C,
+    f: F,
+}
+
+impl<C: fmt::Debug, F> fmt::Debug for MapErr<C, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MapErr")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<C, F> MapErr<C, F> {
+    /// Consumes the adapter, returning the wrapped codec.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C: EncoderError, F> EncoderError for MapErr<C, F> {
+    type Error = C::Error;
+}
+
+impl<Item, C, F> Encoder<Item> for MapErr<C, F>
+where
+    Item: ?Sized,
+    C: Encoder<Item>,
+{
+    fn encode(&mut self, item: &Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.inner.encode(item, dst)
+    }
+
+    fn encode_vectored(&mut self, item: &Item, dst: &mut WriteBuffer) -> Result<(), Self::Error> {
+        self.inner.encode_vectored(item, dst)
+    }
+}
+
+impl<C, F, E> Decoder for MapErr<C, F>
+where
+    C: Decoder,
+    F: FnMut(C::Error) -> E,
+    E: std::error::Error + 'static,
+{
+    type Item = C::Item;
+    type Error = E;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode(src).map_err(&mut self.f)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode_eof(src).map_err(&mut self.f)
+    }
+}
+
+impl<C, F, E> DecoderWithSkipAhead for MapErr<C, F>
+where
+    C: DecoderWithSkipAhead,
+    F: FnMut(C::Error) -> E,
+    E: std::error::Error + 'static,
+{
+    type Handler = C::Handler;
+
+    fn prepare_skip_ahead(&mut self, src: &mut BytesMut) -> Self::Handler {
+        self.inner.prepare_skip_ahead(src)
+    }
+}
+
+/// Encoder adapter created by [`EncoderExt::contramap`].
+pub struct Contramap<C, F, I> {
+    inner: C,
+    f: F,
+    _item: PhantomData<fn() -> I>,
+}
+
+impl<C: fmt::Debug, F, I> fmt::Debug for Contramap<C, F, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Contramap")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<C, F, I> Contramap<C, F, I> {
+    /// Consumes the adapter, returning the wrapped codec.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C: Decoder, F, I> Decoder for Contramap<C, F, I> {
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode(src)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode_eof(src)
+    }
+}
+
+impl<C: DecoderWithSkipAhead, F, I> DecoderWithSkipAhead for Contramap<C, F, I> {
+    type Handler = C::Handler;
+
+    fn prepare_skip_ahead(&mut self, src: &mut BytesMut) -> Self::Handler {
+        self.inner.prepare_skip_ahead(src)
+    }
+}
+
+impl<C: Clone, F: Clone, I> Clone for Contramap<C, F, I> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            f: self.f.clone(),
+            _item: PhantomData,
+        }
+    }
+}
+
+impl<C: EncoderError, F, I> EncoderError for Contramap<C, F, I> {
+    type Error = C::Error;
+}
+
+impl<T, C, F, I> Encoder<T> for Contramap<C, F, I>
+where
+    T: ?Sized,
+    C: Encoder<I>,
+    F: FnMut(&T) -> I,
+{
+    fn encode(&mut self, item: &T, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.inner.encode(&(self.f)(item), dst)
+    }
+
+    fn encode_vectored(&mut self, item: &T, dst: &mut WriteBuffer) -> Result<(), Self::Error> {
+        self.inner.encode_vectored(&(self.f)(item), dst)
+    }
+}
+
+/// Encoder adapter created by [`EncoderExt::map_encode_err`].
+#[derive(Clone)]
+pub struct MapEncodeErr<C, F> {
+    inner: C,
+    f: F,
+}
+
+impl<C: fmt::Debug, F> fmt::Debug for MapEncodeErr<C, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MapEncodeErr")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<C, F> MapEncodeErr<C, F> {
+    /// Consumes the adapter, returning the wrapped codec.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C: Decoder, F> Decoder for MapEncodeErr<C, F> {
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode(src)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode_eof(src)
+    }
+}
+
+impl<C: DecoderWithSkipAhead, F> DecoderWithSkipAhead for MapEncodeErr<C, F> {
+    type Handler = C::Handler;
+
+    fn prepare_skip_ahead(&mut self, src: &mut BytesMut) -> Self::Handler {
+        self.inner.prepare_skip_ahead(src)
+    }
+}
+
+impl<C, F, E> EncoderError for MapEncodeErr<C, F>
+where
+    C: EncoderError,
+    F: FnMut(C::Error) -> E,
+    E: std::error::Error + 'static,
+{
+    type Error = E;
+}
+
+impl<Item, C, F, E> Encoder<Item> for MapEncodeErr<C, F>
+where
+    Item: ?Sized,
+    C: Encoder<Item>,
+    F: FnMut(<C as EncoderError>::Error) -> E,
+    E: std::error::Error + 'static,
+{
+    fn encode(&mut self, item: &Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.inner.encode(item, dst).map_err(&mut self.f)
+    }
+
+    fn encode_vectored(&mut self, item: &Item, dst: &mut WriteBuffer) -> Result<(), Self::Error> {
+        self.inner.encode_vectored(item, dst).map_err(&mut self.f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Length, Limit, LimitError, OverflowError};
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("my error")]
+    struct MyError;
+
+    #[test]
+    fn map_decode() {
+        let mut codec = Length::<u8>::new().map(|b| b.len());
+        let mut src = BytesMut::from(&[3, 1, 2, 3, 0][..]);
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(3));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(0));
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+    }
+
+    #[test]
+    fn and_then_decode() {
+        let mut codec = Length::<u8>::new().and_then(|b| String::from_utf8(b.to_vec()));
+        let mut src = BytesMut::from(&[1, 0xff, 2, b'o', b'k'][..]);
+        assert!(matches!(codec.decode(&mut src), Err(AndThenError::Map(_))));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some("ok".to_string()));
+    }
+
+    #[test]
+    fn contramap_and_map_encode_err() {
+        let mut codec = Length::<u8>::new()
+            .contramap(|n: &usize| vec![0u8; *n])
+            .map_encode_err(|OverflowError| MyError);
+        let mut dst = BytesMut::new();
+        codec.encode(&2, &mut dst).unwrap();
+        assert_eq!(&dst[..], &[2, 0, 0]);
+        assert!(codec.encode(&256, &mut dst).is_err());
+    }
+
+    #[test]
+    fn map_err_decode() {
+        let mut codec = Length::<u64>::new()
+            .and_then(|b| String::from_utf8(b.to_vec()))
+            .map_err(|_| MyError);
+        let mut src = BytesMut::from(&[0, 0, 0, 0, 0, 0, 0, 1, 0xff][..]);
+        assert!(matches!(codec.decode(&mut src), Err(MyError)));
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn limit_recovers() {
+        let mut codec = Limit::new(Length::<u8>::new().map(|b| b.to_vec()), 4);
+        let mut src = BytesMut::from(&[8, 0, 0, 0, 0, 0][..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(LimitError::LimitExceeded(_))
+        ));
+        src.extend_from_slice(&[0, 0, 0, 1, 42]);
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(vec![42]));
+    }
+}
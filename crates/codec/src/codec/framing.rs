@@ -0,0 +1,290 @@
+use super::{Decoder, DecoderWithSkipAhead, Encoder, EncoderError, Payload};
+use crate::WriteBuffer;
+use bytes::{Bytes, BytesMut};
+
+/// A codec which uses a framing codec `F` to split the stream into frames,
+/// and a serialization codec `S` to encode and decode the contents of a single frame.
+///
+/// This is useful for codecs like [`Json`](super::Json) or [`Cbor`](super::Cbor),
+/// which would otherwise need to find the frame boundaries by parsing the
+/// stream, and thus can't recover from corrupt input. The serialization codec
+/// is only invoked on complete frames, and each frame must contain exactly one item.
+///
+/// If the framing codec supports skipping ahead, this codec does too,
+/// which allows wrapping it in [`Limit`](super::Limit).
+///
+/// The serialization codec decodes each frame from a separate buffer, via
+/// [`Decoder::decode_eof`]. Frames which are returned as [`BytesMut`] are used
+/// as-is, other frames (e.g. the [`Bytes`] returned by [`Length`](super::Length))
+/// are copied into a new buffer once, because `Bytes` can't be converted
+/// into a `BytesMut` without copying.
+///
+/// The same serialization codec is used for all frames, so it must not keep
+/// any state from one frame to the next, even if decoding a frame failed or
+/// it didn't contain a complete item. The serialization codecs of this crate
+/// (e.g. [`Json`](super::Json), [`LinesCodec`](super::LinesCodec)) fulfil this.
+///
+/// # Example
+///
+/// ```
+/// use yz_futures_codec::codec::{Decoder, Encoder, Framing, Length, Lines};
+/// use bytes::BytesMut;
+///
+/// let mut codec = Framing::new(Length::<u16>::new(), Lines);
+/// let mut buf = BytesMut::new();
+/// codec.encode("hello\n", &mut buf).unwrap();
+/// assert_eq!(&buf[..], b"\x00\x06hello\n");
+/// assert_eq!(codec.decode(&mut buf).unwrap(), Some("hello\n".to_string()));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Framing<F, S> {
+    /// the codec used to split the stream into frames
+    pub framing: F,
+    /// the codec used to encode and decode the contents of a frame
+    pub serialization: S,
+}
+
+impl<F, S> Framing<F, S> {
+    /// Creates a new `Framing` codec from the given framing and serialization codecs.
+    pub fn new(framing: F, serialization: S) -> Self {
+        Self {
+            framing,
+            serialization,
+        }
+    }
+}
+
+/// the error returned if [`Framing`] fails
+#[derive(Debug, thiserror::Error)]
+pub enum FramingError<F: std::error::Error + 'static, S: std::error::Error + 'static> {
+    /// the framing codec failed
+    #[error("framing failed: {0}")]
+    Framing(#[source] F),
+
+    /// the serialization codec failed
+    #[error("serialization failed: {0}")]
+    Serialization(#[source] S),
+
+    /// the frame didn't contain a complete item
+    #[error("incomplete item in frame")]
+    Incomplete,
+
+    /// the frame contained trailing bytes after the item
+    #[error("{0} trailing bytes in frame")]
+    TrailingBytes(usize),
+}
+
+type DecodeError<F, S> = FramingError<<F as Decoder>::Error, <S as Decoder>::Error>;
+
+impl<F, S> Framing<F, S>
+where
+    F: Decoder,
+    F::Item: Payload,
+    S: Decoder,
+{
+    fn decode_frame(&mut self, frame: F::Item) -> Result<S::Item, DecodeError<F, S>> {
+        let mut frame = frame.into_bytes_mut();
+        let item = self
+            .serialization
+            .decode_eof(&mut frame)
+            .map_err(FramingError::Serialization)?
+            .ok_or(FramingError::Incomplete)?;
+        if frame.is_empty() {
+            Ok(item)
+        } else {
+            Err(FramingError::TrailingBytes(frame.len()))
+        }
+    }
+}
+
+impl<F, S> Decoder for Framing<F, S>
+where
+    F: Decoder,
+    F::Item: Payload,
+    S: Decoder,
+{
+    type Item = S::Item;
+    type Error = DecodeError<F, S>;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.framing.decode(src).map_err(FramingError::Framing)? {
+            Some(frame) => self.decode_frame(frame).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self
+            .framing
+            .decode_eof(src)
+            .map_err(FramingError::Framing)?
+        {
+            Some(frame) => self.decode_frame(frame).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<F, S> DecoderWithSkipAhead for Framing<F, S>
+where
+    F: DecoderWithSkipAhead,
+    F::Item: Payload,
+    S: Decoder,
+{
+    type Handler = F::Handler;
+
+    fn prepare_skip_ahead(&mut self, src: &mut BytesMut) -> Self::Handler {
+        self.framing.prepare_skip_ahead(src)
+    }
+}
+
+impl<F: EncoderError, S: EncoderError> EncoderError for Framing<F, S> {
+    type Error = FramingError<F::Error, S::Error>;
+}
+
+impl<Item, F, S> Encoder<Item> for Framing<F, S>
+where
+    Item: ?Sized,
+    F: Encoder<Bytes>,
+    S: Encoder<Item>,
+{
+    fn encode(&mut self, item: &Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let frame = self.serialize(item)?;
+        self.framing
+            .encode(&frame, dst)
+            .map_err(FramingError::Framing)
+    }
+
+    fn encode_vectored(&mut self, item: &Item, dst: &mut WriteBuffer) -> Result<(), Self::Error> {
+        let frame = self.serialize(item)?;
+        self.framing
+            .encode_vectored(&frame, dst)
+            .map_err(FramingError::Framing)
+    }
+}
+
+impl<F: EncoderError, S> Framing<F, S> {
+    fn serialize<Item>(&mut self, item: &Item) -> Result<Bytes, FramingError<F::Error, S::Error>>
+    where
+        Item: ?Sized,
+        S: Encoder<Item>,
+    {
+        let mut frame = BytesMut::new();
+        self.serialization
+            .encode(item, &mut frame)
+            .map_err(FramingError::Serialization)?;
+        Ok(frame.freeze())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Length, Limit, LimitError, Lines, LinesCodec, LinesCodecError};
+
+    #[test]
+    fn lines_in_length() {
+        let mut codec = Framing::new(Length::<u8>::new(), Lines);
+        let mut src = BytesMut::from(&b"\x03ab\n\x02ab\x04a\nb\n"[..]);
+        assert_eq!(codec.decode(&mut src).unwrap(), Some("ab\n".to_string()));
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(FramingError::Incomplete)
+        ));
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(FramingError::TrailingBytes(2))
+        ));
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn lines_codec_after_incomplete_frame() {
+        let mut codec = Framing::new(Length::<u8>::new(), LinesCodec::new());
+        let mut src = BytesMut::from(&b"\x03abc\x02x\n"[..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(FramingError::Incomplete)
+        ));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some("x\n".to_string()));
+    }
+
+    #[test]
+    fn lines_codec_after_too_long_frame() {
+        let mut codec = Framing::new(Length::<u8>::new(), LinesCodec::new().with_max_length(2));
+        let mut src = BytesMut::from(&b"\x03abc\x02x\n"[..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(FramingError::Serialization(
+                LinesCodecError::MaxLengthExceeded
+            ))
+        ));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some("x\n".to_string()));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_recovers_from_corrupt_frame() {
+        use crate::codec::Json;
+
+        let mut codec = Framing::new(Length::<u16>::new(), Json::<u32, u32>::new());
+        let mut src = BytesMut::new();
+        codec.encode(&1, &mut src).unwrap();
+        src.extend_from_slice(b"\x00\x02{]");
+        codec.encode(&2, &mut src).unwrap();
+
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(1));
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(FramingError::Serialization(_))
+        ));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(2));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_after_incomplete_frame() {
+        use crate::codec::Json;
+
+        let mut codec = Framing::new(Length::<u8>::new(), Json::<(), serde_json::Value>::new());
+        let mut src = BytesMut::from(&b"\x03[[1\x02{}\x02{}"[..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(FramingError::Incomplete)
+        ));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(serde_json::json!({})));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(serde_json::json!({})));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_in_limit() {
+        use crate::codec::Cbor;
+
+        let framing = Framing::new(Length::<u16>::new(), Cbor::<String, String>::new());
+        let mut codec = Limit::new(framing.clone(), 16);
+        let mut enc = framing;
+        let mut src = BytesMut::new();
+        enc.encode(&"x".repeat(32), &mut src).unwrap();
+        let rest = src.split_off(20);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(LimitError::LimitExceeded(_))
+        ));
+        src.unsplit(rest);
+        enc.encode(&"short".to_string(), &mut src).unwrap();
+        assert_eq!(codec.decode(&mut src).unwrap(), Some("short".to_string()));
+    }
+
+    #[test]
+    fn lines_in_limit() {
+        let mut codec = Limit::new(Framing::new(Length::<u8>::new(), Lines), 4);
+        let mut src = BytesMut::from(&b"\x08abcdef"[..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(LimitError::LimitExceeded(_))
+        ));
+        src.extend_from_slice(b"g\n\x02a\n");
+        assert_eq!(codec.decode(&mut src).unwrap(), Some("a\n".to_string()));
+    }
+}
@@ -1,11 +1,22 @@
 use super::{Decoder, Encoder};
-use bytes::{Buf, BufMut, BytesMut};
-use serde::{Deserialize, Serialize};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use memchr::memchr;
+use serde::{de::IgnoredAny, Deserialize, Serialize};
 use serde_json::Error;
-use std::marker::PhantomData;
+use std::{fmt, marker::PhantomData};
 
 /// A codec for JSON encoding and decoding using serde_json
 /// Enc is the type to encode, Dec is the type to decode
+///
+/// The decoder keeps track of the structure of the buffered input
+/// between calls, and only invokes serde_json once a complete value
+/// is buffered, which avoids parsing large values over and over again
+/// while they arrive. Malformed values are discarded as a whole.
+///
+/// By default, values are encoded compactly without any separator between
+/// them. [`with_format`](Json::with_format) selects another [`JsonFormat`],
+/// e.g. newline-separated or pretty-printed values, or JSON text sequences.
+///
 /// ```
 /// # use futures_util::{stream::TryStreamExt, io::Cursor};
 /// use serde::{Serialize, Deserialize};
@@ -29,8 +40,263 @@ use std::marker::PhantomData;
 ///     }
 /// });
 /// ```
-pub struct Json<Enc, Dec>(PhantomData<(Enc, Dec)>);
-impl_phantom!(Json<Enc, Dec>);
+pub struct Json<Enc, Dec> {
+    format: JsonFormat,
+    scanner: Scanner,
+    /// whether the record separator of the current record was already consumed
+    /// (only used with [`JsonFormat::Seq`])
+    in_record: bool,
+    _types: PhantomData<(Enc, Dec)>,
+}
+
+/// The format of the values written by [`Json`], and expected when decoding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JsonFormat {
+    /// compact values without any separator (the default)
+    #[default]
+    Compact,
+
+    /// compact values, each followed by a newline
+    ///
+    /// Unlike [`NdJson`](super::NdJson), the decoder doesn't require
+    /// each value to be on a separate line.
+    NewlineSeparated,
+
+    /// pretty-printed values, each followed by a newline
+    Pretty,
+
+    /// JSON text sequences (`application/json-seq`, RFC 7464)
+    ///
+    /// Each value is prefixed with the record separator `0x1E`,
+    /// and followed by a newline. The decoder uses the record separator
+    /// to resynchronize after a malformed or truncated record,
+    /// ignores empty records, and discards anything before the first
+    /// record separator.
+    Seq,
+}
+
+/// the record separator of JSON text sequences
+const RS: u8 = 0x1E;
+
+impl<Enc, Dec> Json<Enc, Dec> {
+    #[allow(missing_docs)]
+    pub const fn new() -> Self {
+        Self {
+            format: JsonFormat::Compact,
+            scanner: Scanner::new(),
+            in_record: false,
+            _types: PhantomData,
+        }
+    }
+
+    /// Sets the format used for encoding and decoding.
+    pub fn with_format(mut self, format: JsonFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Returns the format used for encoding and decoding.
+    pub fn format(&self) -> JsonFormat {
+        self.format
+    }
+
+    /// Splits the first complete value (or malformed record) off `buf`.
+    fn next_value(&mut self, buf: &mut BytesMut, eof: bool) -> Option<BytesMut> {
+        let value = self.split_value(buf, eof);
+        if eof && value.is_none() {
+            // the input ended, so the scan state doesn't belong to `buf` anymore
+            // (which might be reused for unrelated input, e.g. by `Framing`)
+            self.scanner = Scanner::new();
+            self.in_record = false;
+        }
+        value
+    }
+
+    fn split_value(&mut self, buf: &mut BytesMut, eof: bool) -> Option<BytesMut> {
+        if self.format != JsonFormat::Seq {
+            let limit = buf.len();
+            return self.scanner.next_value(buf, limit, eof);
+        }
+
+        loop {
+            if !self.in_record {
+                // discard anything up to and including the next record separator
+                match memchr(RS, buf) {
+                    Some(pos) => buf.advance(pos + 1),
+                    None => {
+                        buf.clear();
+                        return None;
+                    }
+                }
+                self.in_record = true;
+            }
+
+            // the value can't extend beyond the next record separator,
+            // and the part which was already scanned doesn't contain one
+            let scanned = self.scanner.pos;
+            let next = memchr(RS, &buf[scanned..]).map(|i| scanned + i);
+            let limit = next.unwrap_or_else(|| buf.len());
+            if let Some(value) = self.scanner.next_value(buf, limit, eof || next.is_some()) {
+                self.in_record = false;
+                return Some(value);
+            }
+
+            if next.is_none() && (!eof || buf.is_empty()) {
+                return None;
+            }
+            // the record ended without a complete value,
+            // leading whitespace might have been discarded in the meantime
+            let end = memchr(RS, buf).unwrap_or_else(|| buf.len());
+            let record = buf.split_to(end);
+            self.scanner = Scanner::new();
+            self.in_record = false;
+            if !record.is_empty() {
+                // let serde_json report the error
+                return Some(record);
+            }
+        }
+    }
+}
+
+impl<Enc, Dec> Clone for Json<Enc, Dec> {
+    fn clone(&self) -> Self {
+        Self {
+            format: self.format,
+            scanner: self.scanner.clone(),
+            in_record: self.in_record,
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<Enc, Dec> fmt::Debug for Json<Enc, Dec> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Json")
+            .field("format", &self.format)
+            .field("scanner", &self.scanner)
+            .field("in_record", &self.in_record)
+            .finish()
+    }
+}
+
+impl<Enc, Dec> Default for Json<Enc, Dec> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// compares the configuration, not the state of partially decoded input
+impl<Enc, Dec> PartialEq for Json<Enc, Dec> {
+    fn eq(&self, other: &Self) -> bool {
+        self.format == other.format
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ValueKind {
+    /// no value started yet
+    None,
+    /// an object or an array
+    Container,
+    String,
+    /// a number, `true`, `false` or `null`
+    Scalar,
+}
+
+/// Finds the end of the first JSON value in a buffer, incrementally.
+#[derive(Clone, Debug)]
+struct Scanner {
+    kind: ValueKind,
+    /// the amount of bytes of the current value which were already scanned
+    pos: usize,
+    /// the nesting depth inside of containers
+    depth: usize,
+    in_string: bool,
+    /// whether the previous byte was a backslash inside of a string
+    escape: bool,
+}
+
+impl Scanner {
+    const fn new() -> Self {
+        Self {
+            kind: ValueKind::None,
+            pos: 0,
+            depth: 0,
+            in_string: false,
+            escape: false,
+        }
+    }
+
+    /// Splits the first complete value off the first `limit` bytes of `buf`,
+    /// discarding leading whitespace.
+    ///
+    /// Scalars can only be detected as complete when they are followed by
+    /// another byte, except at the end of the input (`eof`).
+    fn next_value(&mut self, buf: &mut BytesMut, mut limit: usize, eof: bool) -> Option<BytesMut> {
+        if self.kind == ValueKind::None {
+            let start = buf[..limit]
+                .iter()
+                .position(|i| !i.is_ascii_whitespace())
+                .unwrap_or(limit);
+            buf.advance(start);
+            limit -= start;
+            let first = *buf[..limit].first()?;
+            self.kind = match first {
+                b'{' | b'[' => ValueKind::Container,
+                b'"' => ValueKind::String,
+                _ => ValueKind::Scalar,
+            };
+            self.pos = 1;
+            self.depth = 1;
+            self.in_string = first == b'"';
+            self.escape = false;
+        }
+
+        let end = match self.scan(&buf[..limit]) {
+            Some(end) => end,
+            None if eof && self.kind == ValueKind::Scalar => limit,
+            None => return None,
+        };
+        *self = Self::new();
+        Some(buf.split_to(end))
+    }
+
+    /// continues scanning the current value, returns its length if it is complete
+    fn scan(&mut self, buf: &[u8]) -> Option<usize> {
+        while let Some(&i) = buf.get(self.pos) {
+            self.pos += 1;
+            if self.in_string {
+                if self.escape {
+                    self.escape = false;
+                } else if i == b'\\' {
+                    self.escape = true;
+                } else if i == b'"' {
+                    self.in_string = false;
+                    if self.kind == ValueKind::String {
+                        return Some(self.pos);
+                    }
+                }
+                continue;
+            }
+            match (self.kind, i) {
+                (ValueKind::Scalar, b'{' | b'[' | b'}' | b']' | b'"' | b',') => {
+                    return Some(self.pos - 1)
+                }
+                (ValueKind::Scalar, _) if i.is_ascii_whitespace() => return Some(self.pos - 1),
+                (ValueKind::Container, b'"') => self.in_string = true,
+                (ValueKind::Container, b'{' | b'[') => self.depth += 1,
+                (ValueKind::Container, b'}' | b']') => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        return Some(self.pos);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
 
 /// Decoder impl parses json objects from bytes
 impl<Enc, Dec> Decoder for Json<Enc, Dec>
@@ -41,25 +307,86 @@ where
     type Error = Error;
 
     fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        // Build streaming JSON iterator over data
-        let de = serde_json::Deserializer::from_slice(&buf);
-        let mut iter = de.into_iter::<Dec>();
-
-        // Attempt to fetch an item and generate response
-        let res = match iter.next() {
-            Some(Ok(v)) => Ok(Some(v)),
-            Some(Err(ref e)) if e.is_eof() => Ok(None),
-            Some(Err(e)) => Err(e),
+        match self.next_value(buf, false) {
+            Some(value) => serde_json::from_slice(&value).map(Some),
             None => Ok(None),
-        };
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.next_value(buf, true) {
+            Some(value) => serde_json::from_slice(&value).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A single complete JSON value, which owns its buffer.
+///
+/// Decoding into `JsonFrame` (via `Json<Enc, JsonFrame>`) doesn't deserialize
+/// the value, which allows deserializing it afterwards into types
+/// which borrow from the frame (e.g. `&str`, `&[u8]` or `&RawValue`),
+/// which avoids allocating each field separately.
+///
+/// ```
+/// use yz_futures_codec::codec::{Decoder, Json, JsonFrame};
+/// use bytes::BytesMut;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Message<'a> {
+///     name: &'a str,
+/// }
+///
+/// let mut codec = Json::<(), JsonFrame>::new();
+/// let mut buf = BytesMut::from(&br#"{"name":"alice"}"#[..]);
+/// let frame = codec.decode(&mut buf).unwrap().unwrap();
+/// let msg: Message<'_> = frame.deserialize().unwrap();
+/// assert_eq!(msg.name, "alice");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonFrame(Bytes);
+
+impl JsonFrame {
+    /// Returns the serialized value.
+    pub fn bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    /// Consumes the frame, returning the serialized value.
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+
+    /// Deserializes the value, borrowing from the frame where possible.
+    pub fn deserialize<'de, T: Deserialize<'de>>(&'de self) -> Result<T, Error> {
+        serde_json::from_slice(&self.0)
+    }
+}
 
-        // Update offset from iterator
-        let offset = iter.byte_offset();
+impl<Enc> Json<Enc, JsonFrame> {
+    fn next_frame(&mut self, buf: &mut BytesMut, eof: bool) -> Result<Option<JsonFrame>, Error> {
+        match self.next_value(buf, eof) {
+            Some(value) => {
+                serde_json::from_slice::<IgnoredAny>(&value)?;
+                Ok(Some(JsonFrame(value.freeze())))
+            }
+            None => Ok(None),
+        }
+    }
+}
 
-        // Advance buffer
-        buf.advance(offset);
+/// Decoder impl splits json values from bytes, without deserializing them
+impl<Enc> Decoder for Json<Enc, JsonFrame> {
+    type Item = JsonFrame;
+    type Error = Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.next_frame(buf, false)
+    }
 
-        res
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.next_frame(buf, true)
     }
 }
 
@@ -76,12 +403,24 @@ where
     Enc: Serialize + 'static,
 {
     fn encode(&mut self, data: &Enc, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        // Encode json
-        let j = serde_json::to_string(data)?;
-
-        // Write to buffer
-        buf.reserve(j.len());
-        buf.put_slice(&j.as_bytes());
+        // Encode json directly into the buffer,
+        // but don't leave a partially encoded value behind
+        let orig_len = buf.len();
+        if self.format == JsonFormat::Seq {
+            buf.put_u8(RS);
+        }
+        let res = if self.format == JsonFormat::Pretty {
+            serde_json::to_writer_pretty((&mut *buf).writer(), data)
+        } else {
+            serde_json::to_writer((&mut *buf).writer(), data)
+        };
+        if let Err(e) = res {
+            buf.truncate(orig_len);
+            return Err(e);
+        }
+        if self.format != JsonFormat::Compact {
+            buf.put_u8(b'\n');
+        }
 
         Ok(())
     }
@@ -92,8 +431,9 @@ mod test {
     use bytes::BytesMut;
     use serde::{Deserialize, Serialize};
 
-    use super::Json;
-    use crate::{Decoder, Encoder};
+    use super::{Json, JsonFormat, JsonFrame};
+    use crate::codec::test_util::{check_encode_error, Failing};
+    use crate::codec::{Decoder, Encoder};
 
     #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
     struct TestStruct {
@@ -110,7 +450,7 @@ mod test {
             name: "Test name".to_owned(),
             data: 16,
         };
-        codec.encode(item1.clone(), &mut buff).unwrap();
+        codec.encode(&item1, &mut buff).unwrap();
 
         let item2 = codec.decode(&mut buff).unwrap().unwrap();
         assert_eq!(item1, item2);
@@ -129,7 +469,7 @@ mod test {
             name: "Test name".to_owned(),
             data: 34,
         };
-        codec.encode(item1.clone(), &mut buff).unwrap();
+        codec.encode(&item1, &mut buff).unwrap();
 
         let mut start = buff.clone().split_to(4);
         assert_eq!(codec.decode(&mut start).unwrap(), None);
@@ -138,4 +478,118 @@ mod test {
 
         assert_eq!(buff.len(), 0);
     }
+
+    #[test]
+    fn json_codec_scalars_and_garbage() {
+        let mut codec = Json::<(), serde_json::Value>::new();
+        let mut buff = BytesMut::from(&b" 12 \"a\\\"]\"}[1,{\"b\":\"]\"}] 3"[..]);
+
+        assert_eq!(codec.decode(&mut buff).unwrap(), Some(12.into()));
+        assert_eq!(codec.decode(&mut buff).unwrap(), Some("a\"]".into()));
+        assert!(codec.decode(&mut buff).is_err());
+        assert_eq!(
+            codec.decode(&mut buff).unwrap(),
+            Some(serde_json::json!([1, {"b": "]"}]))
+        );
+        assert_eq!(codec.decode(&mut buff).unwrap(), None);
+        assert_eq!(codec.decode_eof(&mut buff).unwrap(), Some(3.into()));
+        assert_eq!(buff.len(), 0);
+    }
+
+    #[test]
+    fn json_codec_eq_ignores_state() {
+        let mut codec = Json::<(), serde_json::Value>::new();
+        let mut buff = BytesMut::from(&b"[1,"[..]);
+        assert_eq!(codec.decode(&mut buff).unwrap(), None);
+        assert_eq!(codec, Json::new());
+        assert_ne!(codec, Json::new().with_format(JsonFormat::Seq));
+    }
+
+    #[test]
+    fn json_codec_formats() {
+        let item = TestStruct {
+            name: "Test\nname".to_owned(),
+            data: 16,
+        };
+        for (format, expected) in [
+            (
+                JsonFormat::Compact,
+                "{\"name\":\"Test\\nname\",\"data\":16}",
+            ),
+            (
+                JsonFormat::NewlineSeparated,
+                "{\"name\":\"Test\\nname\",\"data\":16}\n",
+            ),
+            (
+                JsonFormat::Pretty,
+                "{\n  \"name\": \"Test\\nname\",\n  \"data\": 16\n}\n",
+            ),
+            (
+                JsonFormat::Seq,
+                "\x1e{\"name\":\"Test\\nname\",\"data\":16}\n",
+            ),
+        ] {
+            let mut codec = Json::<TestStruct, TestStruct>::new().with_format(format);
+            let mut buff = BytesMut::new();
+            codec.encode(&item, &mut buff).unwrap();
+            codec.encode(&item, &mut buff).unwrap();
+            assert_eq!(buff, expected.repeat(2).as_bytes(), "{:?}", format);
+
+            assert_eq!(codec.decode(&mut buff).unwrap(), Some(item.clone()));
+            assert_eq!(codec.decode(&mut buff).unwrap(), Some(item.clone()));
+            assert_eq!(codec.decode_eof(&mut buff).unwrap(), None);
+            assert_eq!(buff.len(), 0);
+        }
+    }
+
+    #[test]
+    fn json_seq_resync() {
+        let mut codec = Json::<(), serde_json::Value>::new().with_format(JsonFormat::Seq);
+        let mut buff =
+            BytesMut::from(&b"junk\x1e1\n\x1e{\"a\":[\n\x1e \n\x1e\x1e\"b\"\n\x1e2 3\n\x1e[4"[..]);
+
+        assert_eq!(codec.decode(&mut buff).unwrap(), Some(1.into()));
+        assert!(codec.decode(&mut buff).is_err());
+        assert_eq!(codec.decode(&mut buff).unwrap(), Some("b".into()));
+        assert_eq!(codec.decode(&mut buff).unwrap(), Some(2.into()));
+        // the rest of the record is discarded
+        assert_eq!(codec.decode(&mut buff).unwrap(), None);
+        assert_eq!(&buff[..], b"[4");
+        buff.extend_from_slice(b"]\n");
+        assert_eq!(
+            codec.decode(&mut buff).unwrap(),
+            Some(serde_json::json!([4]))
+        );
+        buff.extend_from_slice(b"\x1e{");
+        assert!(codec.decode_eof(&mut buff).is_err());
+        assert_eq!(codec.decode_eof(&mut buff).unwrap(), None);
+        assert_eq!(buff.len(), 0);
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Borrowing<'a> {
+        name: &'a str,
+        raw: &'a serde_json::value::RawValue,
+    }
+
+    #[test]
+    fn json_codec_borrowing_decode() {
+        let mut codec = Json::<(), JsonFrame>::new();
+        let mut buff = BytesMut::from(&br#"{"name":"a","raw":[1, 2]} {"#[..]);
+
+        let frame = codec.decode(&mut buff).unwrap().unwrap();
+        let item: Borrowing<'_> = frame.deserialize().unwrap();
+        assert_eq!(item.name, "a");
+        assert_eq!(item.raw.get(), "[1, 2]");
+        assert_eq!(item.name.as_ptr(), frame.bytes()[9..].as_ptr());
+
+        // leading whitespace is discarded
+        assert_eq!(codec.decode(&mut buff).unwrap(), None);
+        assert_eq!(&buff[..], b"{");
+    }
+
+    #[test]
+    fn json_codec_encode_error() {
+        check_encode_error(Json::<Failing, ()>::new());
+    }
 }
@@ -1,16 +1,23 @@
-use super::{Decoder, Encoder};
-use bytes::{Buf, Bytes, BytesMut};
+use super::{Decoder, Encoder, Payload};
+use crate::WriteBuffer;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::convert::TryFrom;
 use std::marker::PhantomData;
 
 /// A simple `Codec` implementation sending your data by prefixing it by its length.
 ///
+/// The length header is encoded as specified by the [`LengthType`] `L`,
+/// i.e. either as a fixed-width big-endian integer (`u8`, `u16`, `u32`, `u64`),
+/// or as an unsigned [`Varint`].
+///
+/// Payloads sent as [`Bytes`] aren't copied into the write buffer.
+///
 /// # Example
 ///
 /// This codec will most likely be used wrapped in another codec like so.
 ///
 /// ```
-/// use yz_futures_codec::codec::{Decoder, Encoder, EncoderError, Length, OverflowError};
+/// use yz_futures_codec::codec::{Decoder, Encoder, EncoderError, Length, LengthError, OverflowError};
 /// use bytes::{Bytes, BytesMut};
 /// use std::io::{Error, ErrorKind};
 ///
@@ -31,6 +38,12 @@ use std::marker::PhantomData;
 ///     }
 /// }
 ///
+/// impl From<LengthError> for MyError {
+///     fn from(_: LengthError) -> MyError {
+///         MyError::Overflow
+///     }
+/// }
+///
 /// impl EncoderError for MyStringCodec {
 ///     type Error = MyError;
 /// }
@@ -67,36 +80,68 @@ impl_phantom!(Length<L>);
 #[error("length overflow")]
 pub struct OverflowError;
 
-impl<L> Length<L> {
-    const HEADER_LEN: usize = std::mem::size_of::<L>();
+/// the error returned if [`Length`] fails to decode a length header
+#[derive(Debug, thiserror::Error)]
+pub enum LengthError {
+    /// the length doesn't fit into an `u64` (or `usize`)
+    #[error("length overflow")]
+    Overflow,
+
+    /// the length header is longer than necessary,
+    /// e.g. a [`Varint`] with trailing zero bytes
+    #[error("overlong length header")]
+    Overlong,
 }
 
-/// invariant: `std::mem::size_of::<Self>()` has the same length as the serialization of `Self`
+impl From<OverflowError> for LengthError {
+    fn from(_: OverflowError) -> Self {
+        LengthError::Overflow
+    }
+}
+
+/// The encoding of the length header used by [`Length`].
 pub trait LengthType {
+    /// the maximum length of the serialized header, in bytes
+    const MAX_HEADER_LEN: usize;
+
     /// this method should write the given `x` into the destination buffer
     fn encode(x: usize, dst: &mut BytesMut) -> Result<(), OverflowError>;
 
-    /// this method should decode the length from the buffer
+    /// this method should decode the length from the start of the buffer
     /// (it shouldn't and can't discard it, tho)
     ///
-    /// pre-condition: `src.len() >= std::mem::size_of::<Self>()`
-    fn start_decode(src: &[u8]) -> u64;
+    /// returns the decoded length and the length of the header,
+    /// or `None` if the header isn't completely buffered yet
+    fn start_decode(src: &[u8]) -> Result<Option<(u64, usize)>, LengthError>;
+
+    /// this method should return the length of the invalid header at the start
+    /// of the buffer, after `start_decode` returned an error
+    ///
+    /// The header is discarded, so that decoding can continue after it.
+    fn invalid_header_len(src: &[u8]) -> usize {
+        Self::MAX_HEADER_LEN.min(src.len())
+    }
 }
 
 macro_rules! impl_length {
     ($($x:ty => $y:expr),+ $(,)?) => {
         $(
         impl LengthType for $x {
+            const MAX_HEADER_LEN: usize = $y;
+
             fn encode(x: usize, dst: &mut BytesMut) -> Result<(), OverflowError> {
                 let this = Self::try_from(x).map_err(|_| OverflowError)?;
                 dst.extend_from_slice(&Self::to_be_bytes(this));
                 Ok(())
             }
 
-            fn start_decode(src: &[u8]) -> u64 {
+            fn start_decode(src: &[u8]) -> Result<Option<(u64, usize)>, LengthError> {
+                if src.len() < $y {
+                    return Ok(None);
+                }
                 let mut len_bytes = [0u8; $y];
                 len_bytes.copy_from_slice(&src[..$y]);
-                Self::from_be_bytes(len_bytes).into()
+                Ok(Some((Self::from_be_bytes(len_bytes).into(), $y)))
             }
         }
         )+
@@ -105,60 +150,154 @@ macro_rules! impl_length {
 
 impl_length!(u8 => 1, u16 => 2, u32 => 4, u64 => 8);
 
+/// An unsigned variable-length integer (LEB128), as used e.g. by
+/// length-delimited protobuf streams and libp2p.
+///
+/// Each byte holds 7 bits of the length, starting with the least significant ones,
+/// and the most significant bit of each byte is set if another byte follows.
+/// Encodings which are longer than necessary (e.g. `[0x80, 0x00]` for `0`)
+/// are rejected with [`LengthError::Overlong`], and values which don't fit
+/// into an `u64` with [`LengthError::Overflow`].
+///
+/// ```
+/// use yz_futures_codec::codec::{Encoder, Length, Varint};
+/// use bytes::BytesMut;
+///
+/// let mut buf = BytesMut::new();
+/// Length::<Varint>::new().encode(&[0u8; 300][..], &mut buf).unwrap();
+/// assert_eq!(&buf[..2], &[0xac, 0x02]);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Varint;
+
+impl LengthType for Varint {
+    const MAX_HEADER_LEN: usize = 10;
+
+    fn encode(x: usize, dst: &mut BytesMut) -> Result<(), OverflowError> {
+        let mut x = u64::try_from(x).map_err(|_| OverflowError)?;
+        while x >= 0x80 {
+            dst.put_u8(x as u8 | 0x80);
+            x >>= 7;
+        }
+        dst.put_u8(x as u8);
+        Ok(())
+    }
+
+    fn start_decode(src: &[u8]) -> Result<Option<(u64, usize)>, LengthError> {
+        let mut x = 0u64;
+        for (n, &byte) in src.iter().enumerate().take(Self::MAX_HEADER_LEN) {
+            // the last byte can only hold the most significant bit of an u64
+            if n == Self::MAX_HEADER_LEN - 1 {
+                if byte & 0x80 != 0 {
+                    return Err(LengthError::Overlong);
+                } else if byte > 1 {
+                    return Err(LengthError::Overflow);
+                }
+            }
+            x |= u64::from(byte & 0x7f) << (7 * n);
+            if byte & 0x80 == 0 {
+                // a trailing zero byte means that the encoding is overlong
+                if byte == 0 && n != 0 {
+                    return Err(LengthError::Overlong);
+                }
+                return Ok(Some((x, n + 1)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn invalid_header_len(src: &[u8]) -> usize {
+        let end = src
+            .iter()
+            .position(|&i| i & 0x80 == 0)
+            .map_or(src.len(), |n| n + 1);
+        end.min(Self::MAX_HEADER_LEN)
+    }
+}
+
 impl<L: LengthType> super::EncoderError for Length<L> {
     type Error = OverflowError;
 }
 
 impl<Item, L> Encoder<Item> for Length<L>
 where
-    Item: AsRef<[u8]> + ?Sized,
+    Item: Payload + ?Sized,
     L: LengthType,
 {
     fn encode(&mut self, src: &Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        let src = src.as_ref();
-        dst.reserve(Self::HEADER_LEN + src.len());
+        let src = src.as_slice();
+        dst.reserve(L::MAX_HEADER_LEN + src.len());
         L::encode(src.len(), dst)?;
         dst.extend_from_slice(src);
         Ok(())
     }
+
+    fn encode_vectored(&mut self, src: &Item, dst: &mut WriteBuffer) -> Result<(), Self::Error> {
+        match src.as_shared() {
+            Some(src) => {
+                L::encode(src.len(), dst.buffer_mut())?;
+                dst.push_bytes(src);
+            }
+            None => self.encode(src, dst.buffer_mut())?,
+        }
+        Ok(())
+    }
 }
 
 impl<L: LengthType> Decoder for Length<L> {
     type Item = Bytes;
-    type Error = OverflowError;
+    type Error = LengthError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        Ok(if src.len() < std::mem::size_of::<L>() {
-            None
-        } else {
-            let len = usize::try_from(L::start_decode(&src)).map_err(|_| OverflowError)?;
-            if src.len() - Self::HEADER_LEN >= len {
-                // Skip the length header we already read.
-                src.advance(Self::HEADER_LEN);
-                Some(src.split_to(len).freeze())
-            } else {
-                None
+        let header = L::start_decode(src).and_then(|header| match header {
+            Some((len, header_len)) => usize::try_from(len)
+                .map(|len| Some((len, header_len)))
+                .map_err(|_| LengthError::Overflow),
+            None => Ok(None),
+        });
+        let (len, header_len) = match header {
+            Ok(Some(x)) => x,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                // discard the invalid header, otherwise it would be
+                // decoded (and rejected) again on the next call
+                src.advance(L::invalid_header_len(src));
+                return Err(e);
             }
+        };
+        Ok(if src.len() - header_len >= len {
+            // Skip the length header we already read.
+            src.advance(header_len);
+            Some(src.split_to(len).freeze())
+        } else {
+            None
         })
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct LenSkipAhead {
-    remaining: u64,
+    /// `None` if the length header couldn't be decoded
+    remaining: Option<u64>,
 }
 
 impl super::SkipAheadHandler for LenSkipAhead {
-    fn continue_skipping(mut self, src: &[u8]) -> Result<(usize, Option<Self>), ()> {
+    fn continue_skipping(self, src: &[u8]) -> Result<(usize, Option<Self>), ()> {
         use std::convert::TryInto;
+        let mut remaining = self.remaining.ok_or(())?;
         Ok(
-            if usize::try_from(self.remaining).map(|rem| src.len() >= rem) == Ok(true) {
-                (self.remaining.try_into().unwrap(), None)
+            if usize::try_from(remaining).map(|rem| src.len() >= rem) == Ok(true) {
+                (remaining.try_into().unwrap(), None)
             } else
-            /* src.len() < self.remaining */
+            /* src.len() < remaining */
             {
-                self.remaining -= u64::try_from(src.len()).unwrap();
-                (src.len(), Some(self))
+                remaining -= u64::try_from(src.len()).unwrap();
+                (
+                    src.len(),
+                    Some(Self {
+                        remaining: Some(remaining),
+                    }),
+                )
             },
         )
     }
@@ -168,14 +307,18 @@ impl<L: LengthType> super::DecoderWithSkipAhead for Length<L> {
     type Handler = LenSkipAhead;
 
     fn prepare_skip_ahead(&mut self, src: &mut BytesMut) -> Self::Handler {
-        assert!(src.len() > std::mem::size_of::<L>());
-
-        let len = L::start_decode(&src);
-
-        // skip the length header we already read.
-        src.advance(Self::HEADER_LEN);
-
-        LenSkipAhead { remaining: len }
+        // the header might be incomplete or invalid if it is larger than the limit,
+        // in which case we can't recover
+        match L::start_decode(src) {
+            Ok(Some((len, header_len))) => {
+                // skip the length header we already read.
+                src.advance(header_len);
+                LenSkipAhead {
+                    remaining: Some(len),
+                }
+            }
+            _ => LenSkipAhead { remaining: None },
+        }
     }
 }
 
@@ -197,5 +340,61 @@ mod tests {
 
             assert!(item == Some(Bytes::from(&[1u8, 2, 3][..])));
         }
+
+        #[test]
+        fn it_decodes_partially_buffered_varints() {
+            let mut codec = Length::<Varint>::new();
+            let mut src = BytesMut::from(&[0x81u8][..]);
+            assert!(codec.decode(&mut src).unwrap().is_none());
+            src.put_u8(0x01);
+            assert!(codec.decode(&mut src).unwrap().is_none());
+            src.put(&[7u8; 129][..]);
+            let item = codec.decode(&mut src).unwrap().unwrap();
+            assert_eq!(item.len(), 129);
+            assert!(src.is_empty());
+        }
+
+        #[test]
+        fn it_rejects_overlong_varints() {
+            let overlong: &[&[u8]] = &[&[0x80, 0x00], &[0xff, 0x80, 0x00], &[0x80; 10]];
+            for src in overlong {
+                assert!(
+                    matches!(Varint::start_decode(src), Err(LengthError::Overlong)),
+                    "{:02x?} wasn't rejected as overlong",
+                    src
+                );
+            }
+            assert!(matches!(
+                Varint::start_decode(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02]),
+                Err(LengthError::Overflow)
+            ));
+        }
+    }
+
+    #[test]
+    fn it_discards_invalid_headers() {
+        let mut codec = Length::<Varint>::new();
+        let mut src = BytesMut::from(&[0x80u8, 0x00, 0x01, 42][..]);
+        assert!(matches!(codec.decode(&mut src), Err(LengthError::Overlong)));
+        assert_eq!(&src[..], &[0x01, 42]);
+        assert_eq!(&codec.decode(&mut src).unwrap().unwrap()[..], &[42]);
+
+        let mut src = BytesMut::from(&[0x80u8; 12][..]);
+        assert!(matches!(codec.decode(&mut src), Err(LengthError::Overlong)));
+        assert_eq!(src.len(), 2);
+    }
+
+    #[test]
+    fn varint_roundtrip() {
+        for &x in &[0, 1, 127, 128, 300, 16_383, 16_384, usize::MAX] {
+            let mut dst = BytesMut::new();
+            Varint::encode(x, &mut dst).unwrap();
+            assert!(dst.len() <= Varint::MAX_HEADER_LEN);
+            assert_eq!(
+                Varint::start_decode(&dst).unwrap(),
+                Some((x as u64, dst.len()))
+            );
+            assert_eq!(Varint::start_decode(&dst[..dst.len() - 1]).unwrap(), None);
+        }
     }
 }
@@ -1,10 +1,15 @@
-use super::{Decoder, Encoder};
-use bytes::{BufMut, BytesMut};
+use super::{Decoder, DecoderWithSkipAhead, Encoder, Payload, SkipAheadHandler};
+use bytes::{BufMut, Bytes, BytesMut};
 use memchr::memchr;
 use std::convert::Infallible;
 
 /// A simple `Codec` implementation that splits up data into lines.
 ///
+/// The decoded lines include the trailing `\n`. If the input ends with an
+/// unterminated line, it isn't decoded, which makes [`Framed`](crate::Framed)
+/// fail with a "bytes remaining in stream" error (see [`EofPolicy::Fail`]).
+/// Use [`LinesCodec`] to configure this behaviour.
+///
 /// ```rust
 /// # futures_lite::future::block_on(async move {
 /// use futures_util::stream::TryStreamExt; // for lines.try_next()
@@ -51,3 +56,383 @@ impl Decoder for Lines {
         }
     }
 }
+
+/// A `SkipAheadHandler` which discards everything up to and including the next newline.
+#[derive(Clone, Debug)]
+pub struct LineSkipAhead;
+
+impl SkipAheadHandler for LineSkipAhead {
+    fn continue_skipping(self, src: &[u8]) -> Result<(usize, Option<Self>), ()> {
+        Ok(match memchr(b'\n', src) {
+            Some(pos) => (pos + 1, None),
+            None => (src.len(), Some(self)),
+        })
+    }
+}
+
+impl DecoderWithSkipAhead for Lines {
+    type Handler = LineSkipAhead;
+
+    fn prepare_skip_ahead(&mut self, _src: &mut BytesMut) -> Self::Handler {
+        LineSkipAhead
+    }
+}
+
+/// What to do with an unterminated line at the end of the input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EofPolicy {
+    /// don't decode it, which makes [`Framed`](crate::Framed)
+    /// fail with a "bytes remaining in stream" error
+    #[default]
+    Fail,
+
+    /// decode it like a terminated line
+    Return,
+
+    /// silently discard it
+    Drop,
+}
+
+/// What to do with lines which aren't valid UTF-8.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Utf8Policy {
+    /// report them via [`LinesCodecError::Utf8`]
+    #[default]
+    Fail,
+
+    /// replace invalid sequences with `U+FFFD REPLACEMENT CHARACTER`
+    Lossy,
+
+    /// silently discard them
+    Skip,
+}
+
+/// A configurable variant of [`Lines`].
+///
+/// With the default configuration, it behaves exactly like [`Lines`].
+/// It is a separate codec because the configuration needs state, while
+/// `Lines` is a unit struct which is constructed as `Lines` (or `Lines {}`)
+/// in existing code, and because the error types of `Lines` are fixed
+/// (decoding only fails with a `FromUtf8Error`, encoding never fails),
+/// while the options need [`LinesCodecError`]. Adding the options to
+/// `Lines` would break both.
+///
+/// ```rust
+/// # futures_lite::future::block_on(async move {
+/// use futures_util::stream::TryStreamExt;
+/// use yz_futures_codec::{Framed, codec::{EofPolicy, LinesCodec}, Error};
+///
+/// let input = "hello\r\nworld".as_bytes();
+/// let codec = LinesCodec::new()
+///     .with_eof_policy(EofPolicy::Return)
+///     .with_strip_terminator(true)
+///     .with_append_terminator(true)
+///     .with_crlf(true)
+///     .with_max_length(1024);
+/// let mut lines = Framed::new(input, codec);
+/// assert_eq!(lines.try_next().await?, Some("hello".to_string()));
+/// assert_eq!(lines.try_next().await?, Some("world".to_string()));
+/// assert_eq!(lines.try_next().await?, None);
+/// # Ok::<_, Error<_>>(())
+/// # }).unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LinesCodec {
+    eof_policy: EofPolicy,
+    strip_terminator: bool,
+    append_terminator: bool,
+    crlf: bool,
+    max_length: Option<usize>,
+    utf8_policy: Utf8Policy,
+
+    /// whether the rest of the current (too long) line should be discarded
+    discarding: bool,
+    /// the amount of bytes at the start of the buffer already searched for a newline
+    next_index: usize,
+}
+
+/// the error returned if [`LinesCodec`] fails
+#[derive(Debug, thiserror::Error)]
+pub enum LinesCodecError {
+    /// a line was not valid UTF-8
+    #[error(transparent)]
+    Utf8(#[from] std::string::FromUtf8Error),
+
+    /// a line exceeded the maximum length, and was discarded
+    #[error("maximum line length exceeded")]
+    MaxLengthExceeded,
+
+    /// a line to encode contained a newline
+    #[error("line contains a newline")]
+    EmbeddedNewline,
+}
+
+impl LinesCodec {
+    /// Creates a new `LinesCodec` with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets what to do with an unterminated line at the end of the input.
+    pub fn with_eof_policy(mut self, eof_policy: EofPolicy) -> Self {
+        self.eof_policy = eof_policy;
+        self
+    }
+
+    /// Sets whether the terminator is removed from decoded lines
+    /// (default: `false`).
+    pub fn with_strip_terminator(mut self, strip_terminator: bool) -> Self {
+        self.strip_terminator = strip_terminator;
+        self
+    }
+
+    /// Sets whether the terminator is appended to encoded lines
+    /// (default: `false`, which means that lines are encoded as-is).
+    ///
+    /// If enabled, lines which contain a newline are rejected with
+    /// [`LinesCodecError::EmbeddedNewline`]. Together with
+    /// [`with_strip_terminator`](LinesCodec::with_strip_terminator),
+    /// this makes decoding the inverse of encoding.
+    pub fn with_append_terminator(mut self, append_terminator: bool) -> Self {
+        self.append_terminator = append_terminator;
+        self
+    }
+
+    /// Sets whether `\r\n` is treated as a single terminator
+    /// (default: `false`, which means that the `\r` is part of the line).
+    ///
+    /// If enabled, the encoder appends `\r\n` instead of `\n`.
+    pub fn with_crlf(mut self, crlf: bool) -> Self {
+        self.crlf = crlf;
+        self
+    }
+
+    /// Sets the maximum length of a line, excluding the terminator
+    /// (default: unlimited).
+    ///
+    /// Longer lines are discarded up to the next newline,
+    /// and reported via [`LinesCodecError::MaxLengthExceeded`].
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    /// Sets what to do with lines which aren't valid UTF-8.
+    pub fn with_utf8_policy(mut self, utf8_policy: Utf8Policy) -> Self {
+        self.utf8_policy = utf8_policy;
+        self
+    }
+
+    /// returns the length of the terminator at the end of the given line
+    fn terminator_len(&self, line: &[u8]) -> usize {
+        match line {
+            [.., b'\r', b'\n'] if self.crlf => 2,
+            [.., b'\n'] => 1,
+            _ => 0,
+        }
+    }
+
+    fn check_length(&self, len: usize) -> Result<(), LinesCodecError> {
+        match self.max_length {
+            Some(max) if len > max => Err(LinesCodecError::MaxLengthExceeded),
+            _ => Ok(()),
+        }
+    }
+
+    /// removes the terminator from the given line, if configured to do so
+    fn finish_line(&self, mut line: BytesMut) -> Result<BytesMut, LinesCodecError> {
+        let content_len = line.len() - self.terminator_len(&line);
+        self.check_length(content_len)?;
+        if self.strip_terminator {
+            line.truncate(content_len);
+        }
+        Ok(line)
+    }
+
+    /// splits the next line off `src`
+    fn decode_line(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>, LinesCodecError> {
+        loop {
+            match memchr(b'\n', &src[self.next_index..]) {
+                Some(pos) => {
+                    let line = src.split_to(self.next_index + pos + 1);
+                    self.next_index = 0;
+                    if self.discarding {
+                        self.discarding = false;
+                        continue;
+                    }
+                    return self.finish_line(line).map(Some);
+                }
+                None if self.discarding => {
+                    src.clear();
+                    self.next_index = 0;
+                    return Ok(None);
+                }
+                None => {
+                    // a trailing `\r` might be part of the terminator
+                    let len = src.len() - usize::from(self.crlf && src.ends_with(b"\r"));
+                    if let Err(e) = self.check_length(len) {
+                        src.clear();
+                        self.next_index = 0;
+                        self.discarding = true;
+                        return Err(e);
+                    }
+                    self.next_index = src.len();
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// splits the next line off `src`, at the end of the input
+    fn decode_line_eof(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>, LinesCodecError> {
+        let line = match self.decode_line(src) {
+            Ok(Some(line)) => return Ok(Some(line)),
+            Ok(None) => Ok(match self.eof_policy {
+                EofPolicy::Return if !src.is_empty() => Some(src.split()),
+                EofPolicy::Drop => {
+                    src.clear();
+                    None
+                }
+                _ => None,
+            }),
+            Err(e) => Err(e),
+        };
+        // the input ended, so the scan state doesn't belong to `src` anymore
+        // (which might be reused for unrelated input, e.g. by `Framing`)
+        self.next_index = 0;
+        self.discarding = false;
+        line?.map(|line| self.finish_line(line)).transpose()
+    }
+
+    /// converts the given line into a `String`, according to the UTF-8 policy
+    /// (returns `None` if the line should be skipped)
+    fn line_to_string(&self, line: BytesMut) -> Result<Option<String>, LinesCodecError> {
+        match (String::from_utf8(line.to_vec()), self.utf8_policy) {
+            (Ok(line), _) => Ok(Some(line)),
+            (Err(e), Utf8Policy::Fail) => Err(e.into()),
+            (Err(e), Utf8Policy::Lossy) => {
+                Ok(Some(String::from_utf8_lossy(e.as_bytes()).into_owned()))
+            }
+            (Err(_), Utf8Policy::Skip) => Ok(None),
+        }
+    }
+
+    fn encode_line(&self, item: &[u8], dst: &mut BytesMut) -> Result<(), LinesCodecError> {
+        if !self.append_terminator {
+            dst.extend_from_slice(item);
+            return Ok(());
+        }
+        if memchr(b'\n', item).is_some() {
+            return Err(LinesCodecError::EmbeddedNewline);
+        }
+        let terminator: &[u8] = if self.crlf { b"\r\n" } else { b"\n" };
+        dst.reserve(item.len() + terminator.len());
+        dst.put(item);
+        dst.put(terminator);
+        Ok(())
+    }
+}
+
+impl super::EncoderError for LinesCodec {
+    type Error = LinesCodecError;
+}
+
+impl<Item> Encoder<Item> for LinesCodec
+where
+    Item: AsRef<str> + ?Sized,
+{
+    fn encode(&mut self, item: &Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode_line(item.as_ref().as_bytes(), dst)
+    }
+}
+
+impl Decoder for LinesCodec {
+    type Item = String;
+    type Error = LinesCodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        while let Some(line) = self.decode_line(src)? {
+            if let Some(line) = self.line_to_string(line)? {
+                return Ok(Some(line));
+            }
+        }
+        Ok(None)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        while let Some(line) = self.decode_line_eof(src)? {
+            if let Some(line) = self.line_to_string(line)? {
+                return Ok(Some(line));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl DecoderWithSkipAhead for LinesCodec {
+    type Handler = LineSkipAhead;
+
+    fn prepare_skip_ahead(&mut self, _src: &mut BytesMut) -> Self::Handler {
+        // the skipped bytes are removed from the buffer behind our back
+        self.next_index = 0;
+        LineSkipAhead
+    }
+}
+
+/// A variant of [`LinesCodec`] which decodes lines as [`Bytes`],
+/// without copying them or validating them as UTF-8.
+///
+/// It uses the configuration of the given `LinesCodec`,
+/// except for the [`Utf8Policy`].
+///
+/// ```
+/// use yz_futures_codec::codec::{ByteLinesCodec, Decoder, LinesCodec};
+/// use bytes::BytesMut;
+///
+/// let mut codec = ByteLinesCodec::new(LinesCodec::new().with_strip_terminator(true));
+/// let mut buf = BytesMut::from(&b"\xff\xfe\nrest"[..]);
+/// assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"\xff\xfe");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ByteLinesCodec(LinesCodec);
+
+impl ByteLinesCodec {
+    /// Creates a new `ByteLinesCodec` with the configuration of the given `LinesCodec`.
+    pub fn new(config: LinesCodec) -> Self {
+        Self(config)
+    }
+}
+
+impl super::EncoderError for ByteLinesCodec {
+    type Error = LinesCodecError;
+}
+
+impl<Item> Encoder<Item> for ByteLinesCodec
+where
+    Item: Payload + ?Sized,
+{
+    fn encode(&mut self, item: &Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.0.encode_line(item.as_slice(), dst)
+    }
+}
+
+impl Decoder for ByteLinesCodec {
+    type Item = Bytes;
+    type Error = LinesCodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.0.decode_line(src)?.map(BytesMut::freeze))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.0.decode_line_eof(src)?.map(BytesMut::freeze))
+    }
+}
+
+impl DecoderWithSkipAhead for ByteLinesCodec {
+    type Handler = LineSkipAhead;
+
+    fn prepare_skip_ahead(&mut self, src: &mut BytesMut) -> Self::Handler {
+        self.0.prepare_skip_ahead(src)
+    }
+}
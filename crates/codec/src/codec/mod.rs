@@ -1,3 +1,4 @@
+use crate::WriteBuffer;
 use ::bytes::BytesMut;
 
 /// Decoding of frames via buffers, for use with [`Framed`](crate::Framed).
@@ -32,6 +33,18 @@ pub trait EncoderError {
 pub trait Encoder<Item: ?Sized>: EncoderError {
     /// Encodes an item into the `BytesMut` provided by dst.
     fn encode(&mut self, item: &Item, dst: &mut BytesMut) -> Result<(), Self::Error>;
+
+    /// Encodes an item into the `WriteBuffer` provided by dst.
+    ///
+    /// This is the method used by [`Framed`](crate::Framed), it allows encoders
+    /// to append shared [`Bytes`](::bytes::Bytes) chunks without copying them.
+    ///
+    /// # Notes
+    ///
+    /// The default implementation of this method invokes the `Encoder::encode` method.
+    fn encode_vectored(&mut self, item: &Item, dst: &mut WriteBuffer) -> Result<(), Self::Error> {
+        self.encode(item, dst.buffer_mut())
+    }
 }
 
 macro_rules! impl_phantom {
@@ -59,24 +72,43 @@ macro_rules! impl_phantom {
     }
 }
 
+mod payload;
+pub use self::payload::Payload;
+
 mod bytes;
 pub use self::bytes::BytesCodec;
 
 mod length;
-pub use self::length::{Length, OverflowError};
+pub use self::length::{Length, LengthError, LengthType, OverflowError, Varint};
 
 mod lines;
-pub use self::lines::Lines;
+pub use self::lines::{ByteLinesCodec, EofPolicy, Lines, LinesCodec, LinesCodecError, Utf8Policy};
 
 mod limit;
 pub use self::limit::{DecoderWithSkipAhead, Limit, LimitError, SkipAheadHandler};
 
+mod ext;
+pub use self::ext::{
+    AndThen, AndThenError, Contramap, DecoderExt, EncoderExt, Map, MapEncodeErr, MapErr,
+};
+
+mod framing;
+pub use self::framing::{Framing, FramingError};
+
 #[cfg(feature = "json")]
 mod json;
 #[cfg(feature = "json")]
-pub use self::json::Json;
+pub use self::json::{Json, JsonFormat, JsonFrame};
+
+#[cfg(feature = "json")]
+mod ndjson;
+#[cfg(feature = "json")]
+pub use self::ndjson::NdJson;
 
 #[cfg(feature = "cbor")]
 mod cbor;
 #[cfg(feature = "cbor")]
-pub use self::cbor::Cbor;
+pub use self::cbor::{Cbor, CborFrame};
+
+#[cfg(all(test, any(feature = "json", feature = "cbor")))]
+mod test_util;
@@ -0,0 +1,167 @@
+use super::lines::LineSkipAhead;
+use super::{Decoder, DecoderWithSkipAhead, Encoder};
+use bytes::{BufMut, BytesMut};
+use memchr::memchr;
+use serde::{Deserialize, Serialize};
+use serde_json::Error;
+use std::marker::PhantomData;
+
+/// A codec for newline-delimited JSON (NDJSON) encoding and decoding using serde_json
+/// Enc is the type to encode, Dec is the type to decode
+///
+/// Each value is encoded on a single line. Because each line is
+/// decoded separately, a malformed value is reported, and decoding
+/// continues with the next line. Empty lines are ignored, and
+/// the last line doesn't need to be terminated.
+///
+/// ```
+/// # futures_lite::future::block_on(async move {
+/// use futures_util::stream::StreamExt;
+/// use yz_futures_codec::{codec::NdJson, Framed};
+///
+/// let input = "1\n{oops}\n3\n".as_bytes();
+/// let mut framed = Framed::new(input, NdJson::<u32, u32>::new());
+/// assert_eq!(framed.next().await.unwrap().unwrap(), 1);
+/// assert!(framed.next().await.unwrap().is_err());
+/// assert_eq!(framed.next().await.unwrap().unwrap(), 3);
+/// assert!(framed.next().await.is_none());
+/// # });
+/// ```
+pub struct NdJson<Enc, Dec>(PhantomData<(Enc, Dec)>);
+impl_phantom!(NdJson<Enc, Dec>);
+
+fn is_blank(line: &[u8]) -> bool {
+    line.iter().all(u8::is_ascii_whitespace)
+}
+
+/// Decoder impl parses a json value from each line
+impl<Enc, Dec> Decoder for NdJson<Enc, Dec>
+where
+    for<'de> Dec: Deserialize<'de> + 'static,
+{
+    type Item = Dec;
+    type Error = Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        while let Some(pos) = memchr(b'\n', buf) {
+            let line = buf.split_to(pos + 1);
+            if !is_blank(&line) {
+                return serde_json::from_slice(&line).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(item) = self.decode(buf)? {
+            return Ok(Some(item));
+        }
+        let line = buf.split();
+        if is_blank(&line) {
+            Ok(None)
+        } else {
+            serde_json::from_slice(&line).map(Some)
+        }
+    }
+}
+
+impl<Enc, Dec> DecoderWithSkipAhead for NdJson<Enc, Dec>
+where
+    for<'de> Dec: Deserialize<'de> + 'static,
+{
+    type Handler = LineSkipAhead;
+
+    fn prepare_skip_ahead(&mut self, _src: &mut BytesMut) -> Self::Handler {
+        LineSkipAhead
+    }
+}
+
+impl<Enc, Dec> super::EncoderError for NdJson<Enc, Dec>
+where
+    Enc: Serialize + 'static,
+{
+    type Error = Error;
+}
+
+/// Encoder impl encodes each value on a separate line
+impl<Enc, Dec> Encoder<Enc> for NdJson<Enc, Dec>
+where
+    Enc: Serialize + 'static,
+{
+    fn encode(&mut self, data: &Enc, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        // compact json never contains newlines, because they are escaped in strings
+        let orig_len = buf.len();
+        if let Err(e) = serde_json::to_writer((&mut *buf).writer(), data) {
+            buf.truncate(orig_len);
+            return Err(e);
+        }
+        buf.put_u8(b'\n');
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use serde::{Deserialize, Serialize};
+
+    use super::NdJson;
+    use crate::codec::test_util::{check_encode_error, Failing};
+    use crate::codec::{Decoder, Encoder, Limit, LimitError};
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct TestStruct {
+        pub name: String,
+        pub data: u16,
+    }
+
+    #[test]
+    fn ndjson_codec_encode_decode() {
+        let mut codec = NdJson::<TestStruct, TestStruct>::new();
+        let mut buff = BytesMut::new();
+
+        let item1 = TestStruct {
+            name: "Test\nname".to_owned(),
+            data: 16,
+        };
+        codec.encode(&item1, &mut buff).unwrap();
+        assert_eq!(buff.iter().filter(|&&i| i == b'\n').count(), 1);
+
+        let item2 = codec.decode(&mut buff).unwrap().unwrap();
+        assert_eq!(item1, item2);
+        assert_eq!(codec.decode(&mut buff).unwrap(), None);
+        assert_eq!(buff.len(), 0);
+    }
+
+    #[test]
+    fn ndjson_codec_resync() {
+        let mut codec = NdJson::<(), u16>::new();
+        let mut buff = BytesMut::from(&b"1\n\n[2\r\n3\n4"[..]);
+
+        assert_eq!(codec.decode(&mut buff).unwrap(), Some(1));
+        assert!(codec.decode(&mut buff).is_err());
+        assert_eq!(codec.decode(&mut buff).unwrap(), Some(3));
+        assert_eq!(codec.decode(&mut buff).unwrap(), None);
+        assert_eq!(codec.decode_eof(&mut buff).unwrap(), Some(4));
+        assert_eq!(buff.len(), 0);
+    }
+
+    #[test]
+    fn ndjson_codec_limit() {
+        let mut codec = Limit::new(NdJson::<(), String>::new(), 8);
+        let mut buff = BytesMut::from(&b"\"too long a"[..]);
+
+        assert!(matches!(
+            codec.decode(&mut buff),
+            Err(LimitError::LimitExceeded(_))
+        ));
+        buff.extend_from_slice(b" string\"\n\"ok\"\n");
+        assert_eq!(codec.decode(&mut buff).unwrap(), Some("ok".to_string()));
+    }
+
+    #[test]
+    fn ndjson_codec_encode_error() {
+        check_encode_error(NdJson::<Failing, ()>::new());
+    }
+}
@@ -0,0 +1,151 @@
+use bytes::{Bytes, BytesMut};
+use std::borrow::Cow;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Byte payloads which can be sent via [`BytesCodec`](super::BytesCodec)
+/// and [`Length`](super::Length).
+///
+/// Payloads which are already stored in shared [`Bytes`]
+/// are sent without copying them into the write buffer.
+///
+/// This trait is implemented for the common byte containers of `std` and `bytes`,
+/// and for references and smart pointers to them. Other types which implement
+/// `AsRef<[u8]>` can implement it by forwarding `as_slice` to `as_ref`:
+///
+/// ```
+/// use yz_futures_codec::codec::Payload;
+///
+/// struct Packet(Vec<u8>);
+///
+/// impl Payload for Packet {
+///     fn as_slice(&self) -> &[u8] {
+///         &self.0
+///     }
+/// }
+/// ```
+pub trait Payload {
+    /// Returns the payload as a byte slice.
+    fn as_slice(&self) -> &[u8];
+
+    /// Returns the payload as shared `Bytes`,
+    /// if this is possible without copying it.
+    fn as_shared(&self) -> Option<Bytes> {
+        None
+    }
+
+    /// Converts the payload into a `BytesMut`,
+    /// which copies it unless it already is a `BytesMut`.
+    fn into_bytes_mut(self) -> BytesMut
+    where
+        Self: Sized,
+    {
+        BytesMut::from(self.as_slice())
+    }
+}
+
+impl Payload for Bytes {
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    fn as_shared(&self) -> Option<Bytes> {
+        Some(self.clone())
+    }
+}
+
+macro_rules! impl_payload {
+    ($($t:ty),+ $(,)?) => {
+        $(
+        impl Payload for $t {
+            fn as_slice(&self) -> &[u8] {
+                self.as_ref()
+            }
+        }
+        )+
+    }
+}
+
+impl_payload!([u8], str, Vec<u8>, String);
+
+impl Payload for BytesMut {
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    fn into_bytes_mut(self) -> BytesMut {
+        self
+    }
+}
+
+impl<const N: usize> Payload for [u8; N] {
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+}
+
+macro_rules! impl_payload_deref {
+    ($($t:ty),+ $(,)?) => {
+        $(
+        impl<T: Payload + ?Sized> Payload for $t {
+            fn as_slice(&self) -> &[u8] {
+                (**self).as_slice()
+            }
+
+            fn as_shared(&self) -> Option<Bytes> {
+                (**self).as_shared()
+            }
+        }
+        )+
+    }
+}
+
+impl_payload_deref!(&T, &mut T, Box<T>, Rc<T>, Arc<T>);
+
+impl<T: Payload + ToOwned + ?Sized> Payload for Cow<'_, T> {
+    fn as_slice(&self) -> &[u8] {
+        (**self).as_slice()
+    }
+
+    fn as_shared(&self) -> Option<Bytes> {
+        (**self).as_shared()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn std_payloads() {
+        fn check<P: Payload + ?Sized>(p: &P) {
+            assert_eq!(p.as_slice(), b"abc");
+            assert!(p.as_shared().is_none());
+        }
+        check(&b"abc"[..]);
+        check(b"abc");
+        check("abc");
+        check(&String::from("abc"));
+        check(&Box::<[u8]>::from(&b"abc"[..]));
+        check(&Box::<str>::from("abc"));
+        check(&Arc::<[u8]>::from(&b"abc"[..]));
+        check(&Rc::<str>::from("abc"));
+        check(&Cow::Borrowed(&b"abc"[..]));
+        check(&Cow::<str>::Owned("abc".to_string()));
+    }
+
+    #[test]
+    fn shared_payloads() {
+        let bytes = Bytes::from_static(b"abc");
+        assert_eq!(Arc::new(bytes.clone()).as_shared(), Some(bytes.clone()));
+        assert_eq!(Payload::as_shared(&&bytes), Some(bytes));
+    }
+
+    #[test]
+    fn into_bytes_mut() {
+        let buf = BytesMut::from(&b"abc"[..]);
+        let ptr = buf.as_ptr();
+        assert_eq!(buf.into_bytes_mut().as_ptr(), ptr);
+        assert_eq!(Bytes::from_static(b"abc").into_bytes_mut(), &b"abc"[..]);
+    }
+}
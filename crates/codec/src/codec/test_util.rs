@@ -0,0 +1,25 @@
+//! Helpers shared by the unit tests of the serde based codecs.
+
+use super::Encoder;
+use bytes::BytesMut;
+use serde::Serialize;
+
+/// Fails after serializing a part of itself.
+pub(crate) struct Failing;
+
+impl Serialize for Failing {
+    fn serialize<S: serde::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeSeq};
+        let mut seq = ser.serialize_seq(Some(2))?;
+        seq.serialize_element("partial")?;
+        Err(S::Error::custom("failing"))
+    }
+}
+
+/// Checks that a failed encode doesn't leave partial output behind.
+pub(crate) fn check_encode_error<C: Encoder<Failing>>(mut codec: C) {
+    let mut buff = BytesMut::from(&b"prefix"[..]);
+
+    assert!(codec.encode(&Failing, &mut buff).is_err());
+    assert_eq!(&buff[..], b"prefix");
+}
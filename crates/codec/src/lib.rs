@@ -27,16 +27,19 @@
 #![warn(missing_debug_implementations, rust_2018_idioms)]
 #![warn(clippy::all)]
 
-use bytes::Buf;
 pub use bytes::{Bytes, BytesMut};
-use futures_core::{ready, Stream};
+use futures_core::{ready, stream::FusedStream, Stream};
 use futures_io::{AsyncRead, AsyncWrite};
 use std::task::{Context, Poll};
 use std::{io, ops::Deref, pin::Pin};
 use yz_futures_sink::{FlushSink, Sink};
 
 /// The generic error enum for this crate.
+///
+/// New variants may be added in the future,
+/// so matches on it need a wildcard arm.
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum Error<C: std::error::Error + 'static> {
     /// An error which originated in the codec
     #[error("codec error: {0}")]
@@ -45,12 +48,38 @@ pub enum Error<C: std::error::Error + 'static> {
     /// An error which originated in the underlying I/O object
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
+
+    /// The read buffer reached its maximum size
+    /// without the decoder being able to decode a frame
+    #[error("read buffer limit exceeded (at {0} bytes)")]
+    ReadBufferFull(usize),
 }
 
+mod blocking;
+pub use blocking::FramedSync;
+
 /// Codecs
 pub mod codec;
 use codec::{Decoder, Encoder, EncoderError};
 
+mod read;
+pub use read::{FramedRead, ReadState, ReadStrategy};
+use read::{ReadBuffer, INITIAL_CAPACITY};
+
+mod split;
+pub use split::{ReadHalf, ReuniteError, WriteHalf};
+
+mod stats;
+#[cfg(feature = "stats")]
+pub use stats::Stats;
+use stats::StatsCell;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+mod write;
+pub use write::{FramedWrite, WriteBuffer};
+
 /// A unified `Stream` and `Sink` interface to an underlying I/O object,
 /// using the `Encoder` and `Decoder` traits to encode and decode frames.
 ///
@@ -86,7 +115,7 @@ pub struct Framed<T, U> {
     pub codec: U,
 
     // write
-    w_buffer: BytesMut,
+    w_buffer: WriteBuffer,
     /// The high-water mark for writes, in bytes
     ///
     /// The send *high-water mark* prevents the `Sink` part
@@ -105,7 +134,39 @@ pub struct Framed<T, U> {
     pub w_high_water_mark: usize,
 
     // read
-    r_buffer: BytesMut,
+    r_buffer: ReadBuffer,
+    /// The strategy used to size reads from the underlying I/O object
+    ///
+    /// Data is read directly into the read buffer, in chunks
+    /// whose size is determined by this strategy.
+    ///
+    /// The default is to read in chunks of 8 KiB. Applications which
+    /// receive large frames may wish to use [`ReadStrategy::Adaptive`],
+    /// which grows the chunk size as long as each read fills a whole chunk.
+    pub r_strategy: ReadStrategy,
+    /// The maximum size of the read buffer, in bytes
+    ///
+    /// If the read buffer reaches this size without the decoder
+    /// being able to decode a frame, the `Stream` part stops reading
+    /// from the underlying `AsyncRead` and yields [`Error::ReadBufferFull`].
+    /// This prevents unbounded buffer growth (e.g. caused by a peer which
+    /// sends a never-ending frame), regardless of the codec in use.
+    ///
+    /// The default is `None`, which means that the read buffer is unbounded.
+    pub r_max_buffer_size: Option<usize>,
+    /// Whether codec errors end the stream
+    ///
+    /// If this is `true`, the `Stream` part ends (yields `None`) after
+    /// the decoder returned an error. Otherwise, decoding continues
+    /// on the next poll, which allows decoders like [`Limit`](codec::Limit)
+    /// to recover from invalid frames.
+    ///
+    /// I/O errors always end the stream.
+    ///
+    /// The default is `false`.
+    pub r_end_on_error: bool,
+
+    stats: StatsCell,
 }
 
 impl<T, U> Deref for Framed<T, U> {
@@ -116,8 +177,6 @@ impl<T, U> Deref for Framed<T, U> {
     }
 }
 
-const INITIAL_CAPACITY: usize = 8 * 1024;
-
 impl<T, U> Framed<T, U> {
     /// Creates a new `Framed` transport with the given codec.
     /// A codec is a type which implements `Decoder` and `Encoder`.
@@ -126,17 +185,21 @@ impl<T, U> Framed<T, U> {
             inner,
             codec,
 
-            w_buffer: BytesMut::with_capacity(INITIAL_CAPACITY),
-
-            // 2^17 bytes, which is slightly over 60% of the default
-            // TCP send buffer size (SO_SNDBUF)
-            w_high_water_mark: 131072,
+            w_buffer: WriteBuffer::with_capacity(INITIAL_CAPACITY),
+            w_high_water_mark: write::DEFAULT_HIGH_WATER_MARK,
 
-            r_buffer: BytesMut::with_capacity(INITIAL_CAPACITY),
+            r_buffer: ReadBuffer::new(),
+            r_strategy: ReadStrategy::default(),
+            r_max_buffer_size: None,
+            r_end_on_error: false,
+            stats: StatsCell::default(),
         }
     }
 
     /// Release the I/O and Codec
+    ///
+    /// Note that the contents of the buffers are lost,
+    /// use [`Framed::into_parts`] to retain them.
     pub fn release(self) -> (T, U) {
         (self.inner, self.codec)
     }
@@ -161,7 +224,163 @@ impl<T, U> Framed<T, U> {
 
     /// Returns a reference to the read buffer.
     pub fn read_buffer(&self) -> &BytesMut {
-        &self.r_buffer
+        &self.r_buffer.data
+    }
+
+    /// Returns the state of the `Stream` part.
+    pub fn read_state(&self) -> ReadState {
+        self.r_buffer.state
+    }
+
+    /// Returns the traffic statistics of this `Framed`.
+    ///
+    /// Only available with the `stats` feature, which
+    /// otherwise doesn't add any overhead.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> &Stats {
+        self.stats.get()
+    }
+
+    /// Returns `true` if the `Stream` part won't read from the
+    /// underlying I/O object anymore, e.g. because the peer
+    /// shut down its write direction.
+    ///
+    /// The `Sink` part stays usable regardless of this.
+    pub fn is_read_closed(&self) -> bool {
+        self.r_buffer.state != ReadState::Open
+    }
+
+    /// Consumes the `Framed`, returning its parts,
+    /// including the contents of the buffers.
+    ///
+    /// This can be used together with [`Framed::from_parts`] to
+    /// switch codecs without losing any already buffered data.
+    pub fn into_parts(self) -> FramedParts<T, U> {
+        FramedParts {
+            io: self.inner,
+            codec: self.codec,
+            read_buf: self.r_buffer.data,
+            write_buf: self.w_buffer,
+            w_high_water_mark: self.w_high_water_mark,
+        }
+    }
+
+    /// Creates a `Framed` from its parts,
+    /// (e.g. previously obtained via [`Framed::into_parts`]).
+    ///
+    /// Settings which aren't part of [`FramedParts`] are reset to their defaults.
+    pub fn from_parts(parts: FramedParts<T, U>) -> Self {
+        let FramedParts {
+            io,
+            codec,
+            read_buf,
+            write_buf,
+            w_high_water_mark,
+        } = parts;
+        Self {
+            inner: io,
+            codec,
+            w_buffer: write_buf,
+            w_high_water_mark,
+            r_buffer: read_buf.into(),
+            r_strategy: ReadStrategy::default(),
+            r_max_buffer_size: None,
+            r_end_on_error: false,
+            stats: StatsCell::default(),
+        }
+    }
+
+    /// Replaces the codec, keeping the I/O object and the contents of both buffers.
+    ///
+    /// This is useful to switch to another protocol after a handshake,
+    /// bytes which were already read are handed to the new codec.
+    pub fn map_codec<V, F>(self, f: F) -> Framed<T, V>
+    where
+        F: FnOnce(U) -> V,
+    {
+        Framed {
+            inner: self.inner,
+            codec: f(self.codec),
+            w_buffer: self.w_buffer,
+            w_high_water_mark: self.w_high_water_mark,
+            r_buffer: self.r_buffer,
+            r_strategy: self.r_strategy,
+            r_max_buffer_size: self.r_max_buffer_size,
+            r_end_on_error: self.r_end_on_error,
+            stats: self.stats,
+        }
+    }
+
+    /// Replaces the codec of a pinned `Framed` with another one of the same type,
+    /// keeping the I/O object and the contents of both buffers.
+    /// Returns the previous codec.
+    pub fn replace_codec(self: Pin<&mut Self>, codec: U) -> U {
+        std::mem::replace(self.project().codec, codec)
+    }
+
+    /// Splits the `Framed` into a read half and a write half,
+    /// which can be used independently, e.g. from different tasks.
+    ///
+    /// Each half keeps its own buffer and its own copy of the codec,
+    /// while the I/O object is shared between them, and only locked
+    /// while one of the halves reads from or writes to it.
+    /// Use [`ReadHalf::reunite`] to restore the original `Framed`.
+    pub fn split(self) -> (ReadHalf<T, U>, WriteHalf<T, U>)
+    where
+        T: Unpin,
+        U: Clone,
+    {
+        split::split(self)
+    }
+}
+
+/// The parts of a [`Framed`], obtained via [`Framed::into_parts`].
+///
+/// ```
+/// # futures_lite::future::block_on(async move {
+/// use futures_util::stream::TryStreamExt;
+/// use yz_futures_codec::{codec::{Length, Lines}, Framed, FramedParts};
+///
+/// let input = b"HELLO\n\x02hi".as_ref();
+/// let mut framed = Framed::new(input, Lines);
+/// assert_eq!(framed.try_next().await.unwrap().unwrap(), "HELLO\n");
+///
+/// // switch to another protocol, keeping the already buffered data
+/// let FramedParts { io, read_buf, write_buf, w_high_water_mark, .. } = framed.into_parts();
+/// let mut framed = Framed::from_parts(FramedParts {
+///     io,
+///     codec: Length::<u8>::new(),
+///     read_buf,
+///     write_buf,
+///     w_high_water_mark,
+/// });
+/// assert_eq!(&framed.try_next().await.unwrap().unwrap()[..], b"hi");
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct FramedParts<T, U> {
+    /// the underlying I/O object
+    pub io: T,
+
+    /// the codec used to encode and decode frames
+    pub codec: U,
+
+    /// the buffer with read, but not yet decoded data
+    pub read_buf: BytesMut,
+
+    /// the buffer with encoded, but not yet written data
+    pub write_buf: WriteBuffer,
+
+    /// the high-water mark for writes, in bytes
+    ///
+    /// see [`Framed::w_high_water_mark`]
+    pub w_high_water_mark: usize,
+}
+
+impl<T, U> FramedParts<T, U> {
+    /// Creates new parts with empty buffers and default settings.
+    pub fn new(io: T, codec: U) -> Self {
+        Framed::new(io, codec).into_parts()
     }
 }
 
@@ -169,44 +388,23 @@ impl<T: AsyncRead, U: Decoder> Stream for Framed<T, U> {
     type Item = Result<U::Item, Error<U::Error>>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let mut this = self.project();
-        let mut buf = [0u8; INITIAL_CAPACITY];
-        let mut ended = false;
-
-        loop {
-            match this
-                .codec
-                .decode(&mut this.r_buffer)
-                .map_err(Error::Codec)?
-            {
-                Some(item) => return Poll::Ready(Some(Ok(item))),
-                None if ended => {
-                    return if this.r_buffer.is_empty() {
-                        Poll::Ready(None)
-                    } else {
-                        match this
-                            .codec
-                            .decode_eof(&mut this.r_buffer)
-                            .map_err(Error::Codec)?
-                        {
-                            Some(item) => Poll::Ready(Some(Ok(item))),
-                            None if this.r_buffer.is_empty() => Poll::Ready(None),
-                            None => Poll::Ready(Some(Err(io::Error::new(
-                                io::ErrorKind::UnexpectedEof,
-                                "bytes remaining in stream",
-                            )
-                            .into()))),
-                        }
-                    };
-                }
-                _ => {
-                    let n = ready!(this.inner.as_mut().poll_read(cx, &mut buf))?;
-                    this.r_buffer.extend_from_slice(&buf[..n]);
-                    ended = n == 0;
-                    continue;
-                }
-            }
-        }
+        let this = self.project();
+        read::poll_next_frame(
+            this.inner,
+            cx,
+            this.codec,
+            this.r_buffer,
+            *this.r_strategy,
+            *this.r_max_buffer_size,
+            *this.r_end_on_error,
+            this.stats,
+        )
+    }
+}
+
+impl<T: AsyncRead, U: Decoder> FusedStream for Framed<T, U> {
+    fn is_terminated(&self) -> bool {
+        self.r_buffer.is_terminated()
     }
 }
 
@@ -216,27 +414,23 @@ impl<T: AsyncWrite, U> Framed<T, U> {
         cx: &mut Context<'_>,
         limit: usize,
     ) -> Poll<Result<(), io::Error>> {
-        let mut this = self.project();
-        let orig_len = this.w_buffer.len();
-
-        while this.w_buffer.len() > limit {
-            let num_write = ready!(this.inner.as_mut().poll_write(cx, &this.w_buffer))?;
-
-            if num_write == 0 {
-                return Poll::Ready(Err(io::Error::new(
-                    io::ErrorKind::UnexpectedEof,
-                    "FramedWrite: end of input",
-                )));
-            }
-
-            this.w_buffer.advance(num_write);
-        }
+        let this = self.project();
+        write::poll_flush_until(this.inner, cx, this.w_buffer, limit, this.stats)
+    }
 
-        if orig_len != this.w_buffer.len() {
-            this.inner.poll_flush(cx)
-        } else {
-            Poll::Ready(Ok(()))
-        }
+    /// Flushes the write buffer and closes the write direction
+    /// of the underlying I/O object.
+    ///
+    /// For I/O objects which support half-close (e.g. TCP streams,
+    /// which call `shutdown(Write)` in `poll_close`), the `Stream` part
+    /// stays usable, and can be used to read the remaining frames
+    /// sent by the peer.
+    pub fn poll_close_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        ready!(self.as_mut().poll_flush_until(cx, 0))?;
+        self.project().inner.poll_close(cx)
     }
 }
 
@@ -255,9 +449,8 @@ where
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.poll_flush_until(cx, 0).map_err(Into::into)
     }
-    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        ready!(self.as_mut().poll_flush(cx))?;
-        self.project().inner.poll_close(cx).map_err(Into::into)
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_close_write(cx).map_err(Into::into)
     }
 }
 
@@ -269,6 +462,10 @@ where
 {
     fn start_send(self: Pin<&mut Self>, item: &'a Item) -> Result<(), Self::Error> {
         let this = self.project();
-        this.codec.encode(item, this.w_buffer).map_err(Error::Codec)
+        this.codec
+            .encode_vectored(item, this.w_buffer)
+            .map_err(Error::Codec)?;
+        this.stats.on_encode(this.w_buffer.len());
+        Ok(())
     }
 }
@@ -0,0 +1,385 @@
+use crate::codec::Decoder;
+use crate::stats::StatsCell;
+use crate::Error;
+use bytes::BytesMut;
+use futures_core::{ready, stream::FusedStream, Stream};
+use futures_io::AsyncRead;
+use std::task::{Context, Poll};
+use std::{io, ops::Deref, pin::Pin};
+
+pub(crate) const INITIAL_CAPACITY: usize = 8 * 1024;
+
+/// The strategy used to size the reads from the underlying I/O object.
+///
+/// Data is read directly into the spare capacity of the read buffer,
+/// the chunk size determines how much spare capacity is made available
+/// (and thus, how many bytes are read at most) per `poll_read` call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadStrategy {
+    /// always read in chunks of the given size
+    Exact(usize),
+
+    /// start with chunks of `initial` bytes, and double the chunk size
+    /// whenever a read fills a whole chunk, up to `max` bytes
+    Adaptive {
+        /// the initial chunk size, in bytes
+        initial: usize,
+        /// the maximum chunk size, in bytes
+        max: usize,
+    },
+}
+
+impl Default for ReadStrategy {
+    fn default() -> Self {
+        ReadStrategy::Exact(INITIAL_CAPACITY)
+    }
+}
+
+impl ReadStrategy {
+    fn initial(self) -> usize {
+        match self {
+            ReadStrategy::Exact(x) => x,
+            ReadStrategy::Adaptive { initial, .. } => initial,
+        }
+    }
+
+    fn next(self, chunk_size: usize, num_read: usize) -> usize {
+        match self {
+            ReadStrategy::Exact(x) => x,
+            ReadStrategy::Adaptive { max, .. } if num_read == chunk_size => {
+                std::cmp::min(chunk_size.saturating_mul(2), max)
+            }
+            ReadStrategy::Adaptive { .. } => chunk_size,
+        }
+    }
+}
+
+/// The state of the `Stream` part of framed readers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReadState {
+    /// the underlying I/O object is still open for reading
+    #[default]
+    Open,
+
+    /// the underlying I/O object reached EOF,
+    /// remaining frames are decoded from the read buffer,
+    /// but no further reads happen
+    Eof,
+
+    /// an error occurred which ended the stream
+    Failed,
+}
+
+/// The read buffer and state of framed readers.
+///
+/// `tail` is a zero-initialized buffer (placed directly after `data`
+/// in the same allocation, if possible) which is reused across reads,
+/// which allows reading directly into the buffer without `unsafe` code
+/// and without zeroing the memory on every read.
+#[derive(Debug, Default)]
+pub(crate) struct ReadBuffer {
+    pub(crate) data: BytesMut,
+    tail: BytesMut,
+    chunk_size: Option<usize>,
+    pub(crate) state: ReadState,
+}
+
+impl From<BytesMut> for ReadBuffer {
+    fn from(data: BytesMut) -> Self {
+        Self {
+            data,
+            ..Default::default()
+        }
+    }
+}
+
+impl ReadBuffer {
+    pub(crate) fn new() -> Self {
+        BytesMut::with_capacity(INITIAL_CAPACITY).into()
+    }
+
+    pub(crate) fn is_terminated(&self) -> bool {
+        match self.state {
+            ReadState::Open => false,
+            ReadState::Eof => self.data.is_empty(),
+            ReadState::Failed => true,
+        }
+    }
+
+    /// makes sure that the tail is at least `chunk_size` bytes long
+    fn reserve_tail(&mut self, chunk_size: usize) {
+        if self.tail.len() >= chunk_size {
+            return;
+        }
+        let len = self.data.len();
+        // if `tail` is still contiguous to `data`, this doesn't copy anything
+        self.data.unsplit(std::mem::take(&mut self.tail));
+        if self.data.len() < len + chunk_size {
+            self.data.resize(len + chunk_size, 0);
+        }
+        self.tail = self.data.split_off(len);
+    }
+
+    /// reads into the tail using `read`, which receives a chunk of the tail
+    /// sized according to `strategy`, but at most `limit` bytes
+    fn fill_with<F>(
+        &mut self,
+        strategy: ReadStrategy,
+        limit: usize,
+        read: F,
+    ) -> Poll<io::Result<usize>>
+    where
+        F: FnOnce(&mut [u8]) -> Poll<io::Result<usize>>,
+    {
+        let full_chunk_size = *self.chunk_size.get_or_insert_with(|| strategy.initial());
+        // a chunk size of zero would be misinterpreted as EOF
+        let chunk_size = std::cmp::max(std::cmp::min(full_chunk_size, limit), 1);
+        self.reserve_tail(chunk_size);
+
+        let n = ready!(read(&mut self.tail[..chunk_size]))?;
+        self.data.unsplit(self.tail.split_to(n));
+        if chunk_size == full_chunk_size {
+            self.chunk_size = Some(strategy.next(chunk_size, n));
+        }
+        Poll::Ready(Ok(n))
+    }
+}
+
+type PollFrame<D> = Poll<Option<Result<<D as Decoder>::Item, Error<<D as Decoder>::Error>>>>;
+
+fn fail<I, E>(
+    state: &mut ReadState,
+    err: Error<E>,
+    terminal: bool,
+) -> Poll<Option<Result<I, Error<E>>>>
+where
+    E: std::error::Error + 'static,
+{
+    if terminal {
+        *state = ReadState::Failed;
+    }
+    Poll::Ready(Some(Err(err)))
+}
+
+/// the read loop shared by all framed readers
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn poll_next_frame<T, D>(
+    mut inner: Pin<&mut T>,
+    cx: &mut Context<'_>,
+    codec: &mut D,
+    r_buffer: &mut ReadBuffer,
+    r_strategy: ReadStrategy,
+    r_max_buffer_size: Option<usize>,
+    r_end_on_error: bool,
+    stats: &mut StatsCell,
+) -> PollFrame<D>
+where
+    T: AsyncRead + ?Sized,
+    D: Decoder + ?Sized,
+{
+    next_frame_with(
+        codec,
+        r_buffer,
+        r_strategy,
+        r_max_buffer_size,
+        r_end_on_error,
+        stats,
+        |buf| inner.as_mut().poll_read(cx, buf),
+    )
+}
+
+/// the read loop, reading from the underlying I/O object via `read`
+pub(crate) fn next_frame_with<D, F>(
+    codec: &mut D,
+    r_buffer: &mut ReadBuffer,
+    r_strategy: ReadStrategy,
+    r_max_buffer_size: Option<usize>,
+    r_end_on_error: bool,
+    stats: &mut StatsCell,
+    mut read: F,
+) -> PollFrame<D>
+where
+    D: Decoder + ?Sized,
+    F: FnMut(&mut [u8]) -> Poll<io::Result<usize>>,
+{
+    loop {
+        let ended = match r_buffer.state {
+            ReadState::Open => false,
+            ReadState::Eof => true,
+            ReadState::Failed => return Poll::Ready(None),
+        };
+        let (buf, state) = (&mut r_buffer.data, &mut r_buffer.state);
+
+        match codec.decode(buf) {
+            Ok(Some(item)) => {
+                stats.on_decode();
+                return Poll::Ready(Some(Ok(item)));
+            }
+            Err(e) => return fail(state, Error::Codec(e), r_end_on_error),
+            Ok(None) if ended => {
+                if buf.is_empty() {
+                    return Poll::Ready(None);
+                }
+                return match codec.decode_eof(buf) {
+                    Ok(Some(item)) => {
+                        stats.on_decode();
+                        Poll::Ready(Some(Ok(item)))
+                    }
+                    Ok(None) if buf.is_empty() => Poll::Ready(None),
+                    Ok(None) => fail(
+                        state,
+                        io::Error::new(io::ErrorKind::UnexpectedEof, "bytes remaining in stream")
+                            .into(),
+                        true,
+                    ),
+                    Err(e) => fail(state, Error::Codec(e), r_end_on_error),
+                };
+            }
+            Ok(None) => {
+                let limit = match r_max_buffer_size {
+                    Some(max) if buf.len() >= max => {
+                        return fail(state, Error::ReadBufferFull(buf.len()), true);
+                    }
+                    Some(max) => max - buf.len(),
+                    None => usize::MAX,
+                };
+                match ready!(r_buffer.fill_with(r_strategy, limit, &mut read)) {
+                    Ok(0) => r_buffer.state = ReadState::Eof,
+                    Ok(n) => stats.on_read(n, r_buffer.data.len()),
+                    Err(e) => return fail(&mut r_buffer.state, e.into(), true),
+                }
+            }
+        }
+    }
+}
+
+/// A `Stream` of frames decoded from an underlying [`AsyncRead`],
+/// using the `Decoder` trait.
+///
+/// This is the read-only counterpart of [`Framed`](crate::Framed).
+///
+/// # Example
+/// ```
+/// # futures_lite::future::block_on(async move {
+/// use futures_util::stream::TryStreamExt;
+/// use yz_futures_codec::{codec::Lines, FramedRead, Error};
+///
+/// let input = "hello\nworld\n".as_bytes();
+/// let mut lines = FramedRead::new(input, Lines);
+/// assert_eq!(lines.try_next().await?, Some("hello\n".to_string()));
+/// assert_eq!(lines.try_next().await?, Some("world\n".to_string()));
+/// assert_eq!(lines.try_next().await?, None);
+/// # Ok::<_, Error<_>>(())
+/// # }).unwrap();
+/// ```
+#[pin_project::pin_project]
+#[derive(Debug)]
+pub struct FramedRead<T, D> {
+    #[pin]
+    inner: T,
+
+    /// the codec used to decode frames
+    pub codec: D,
+
+    r_buffer: ReadBuffer,
+    /// The strategy used to size reads from the underlying I/O object
+    ///
+    /// see [`Framed::r_strategy`](crate::Framed::r_strategy)
+    pub r_strategy: ReadStrategy,
+    /// The maximum size of the read buffer, in bytes
+    ///
+    /// see [`Framed::r_max_buffer_size`](crate::Framed::r_max_buffer_size)
+    pub r_max_buffer_size: Option<usize>,
+    /// Whether codec errors end the stream
+    ///
+    /// see [`Framed::r_end_on_error`](crate::Framed::r_end_on_error)
+    pub r_end_on_error: bool,
+
+    stats: StatsCell,
+}
+
+impl<T, D> Deref for FramedRead<T, D> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T, D> FramedRead<T, D> {
+    /// Creates a new `FramedRead` with the given decoder.
+    pub fn new(inner: T, codec: D) -> Self {
+        Self {
+            inner,
+            codec,
+            r_buffer: ReadBuffer::new(),
+            r_strategy: ReadStrategy::default(),
+            r_max_buffer_size: None,
+            r_end_on_error: false,
+            stats: StatsCell::default(),
+        }
+    }
+
+    /// Release the I/O and Codec
+    pub fn release(self) -> (T, D) {
+        (self.inner, self.codec)
+    }
+
+    /// Consumes the `FramedRead`, returning its underlying I/O stream.
+    ///
+    /// Note that care should be taken to not tamper with the underlying stream
+    /// of data coming in as it may corrupt the stream of frames otherwise
+    /// being worked with.
+    pub fn into_inner(self) -> T {
+        self.release().0
+    }
+
+    /// Returns a mutable reference to the underlying I/O stream.
+    ///
+    /// Note that care should be taken to not tamper with the underlying stream
+    /// of data coming in as it may corrupt the stream of frames otherwise
+    /// being worked with.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Returns a reference to the read buffer.
+    pub fn read_buffer(&self) -> &BytesMut {
+        &self.r_buffer.data
+    }
+
+    /// Returns the state of the `Stream` part.
+    pub fn read_state(&self) -> ReadState {
+        self.r_buffer.state
+    }
+
+    /// Returns the traffic statistics of this `FramedRead`.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> &crate::Stats {
+        self.stats.get()
+    }
+}
+
+impl<T: AsyncRead, D: Decoder> Stream for FramedRead<T, D> {
+    type Item = Result<D::Item, Error<D::Error>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        poll_next_frame(
+            this.inner,
+            cx,
+            this.codec,
+            this.r_buffer,
+            *this.r_strategy,
+            *this.r_max_buffer_size,
+            *this.r_end_on_error,
+            this.stats,
+        )
+    }
+}
+
+impl<T: AsyncRead, D: Decoder> FusedStream for FramedRead<T, D> {
+    fn is_terminated(&self) -> bool {
+        self.r_buffer.is_terminated()
+    }
+}
@@ -0,0 +1,291 @@
+use crate::codec::{Decoder, Encoder, EncoderError};
+use crate::read::{self, ReadBuffer, ReadState, ReadStrategy};
+use crate::stats::StatsCell;
+use crate::write::{self, WriteBuffer};
+use crate::{Error, Framed};
+use bytes::BytesMut;
+use futures_core::{ready, stream::FusedStream, Stream};
+use futures_io::{AsyncRead, AsyncWrite};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use std::{fmt, pin::Pin};
+use yz_futures_sink::{FlushSink, Sink};
+
+/// the I/O object shared between both halves of a split [`Framed`]
+///
+/// It is only locked while reading from or writing to it.
+type SharedIo<T> = Arc<Mutex<T>>;
+
+fn lock<T>(io: &SharedIo<T>) -> MutexGuard<'_, T> {
+    io.lock().expect("Framed: shared I/O object poisoned")
+}
+
+pub(crate) fn split<T: Unpin, U: Clone>(framed: Framed<T, U>) -> (ReadHalf<T, U>, WriteHalf<T, U>) {
+    let Framed {
+        inner,
+        codec,
+        w_buffer,
+        w_high_water_mark,
+        r_buffer,
+        r_strategy,
+        r_max_buffer_size,
+        r_end_on_error,
+        stats,
+    } = framed;
+    let io = Arc::new(Mutex::new(inner));
+
+    (
+        ReadHalf {
+            io: io.clone(),
+            codec: codec.clone(),
+            r_buffer,
+            r_strategy,
+            r_max_buffer_size,
+            r_end_on_error,
+            stats: stats.clone(),
+        },
+        WriteHalf {
+            io,
+            codec,
+            w_buffer,
+            w_high_water_mark,
+            stats,
+        },
+    )
+}
+
+/// The read half of a [`Framed`], created by [`Framed::split`].
+///
+/// Implements [`Stream`], using the `Decoder` of its own copy of the codec.
+#[derive(Debug)]
+pub struct ReadHalf<T, U> {
+    io: SharedIo<T>,
+
+    /// the codec used to decode frames
+    pub codec: U,
+
+    r_buffer: ReadBuffer,
+
+    /// The strategy used to size reads from the underlying I/O object
+    ///
+    /// see [`Framed::r_strategy`]
+    pub r_strategy: ReadStrategy,
+
+    /// The maximum size of the read buffer, in bytes
+    ///
+    /// see [`Framed::r_max_buffer_size`]
+    pub r_max_buffer_size: Option<usize>,
+
+    /// Whether codec errors end the stream
+    ///
+    /// see [`Framed::r_end_on_error`]
+    pub r_end_on_error: bool,
+
+    stats: StatsCell,
+}
+
+/// The write half of a [`Framed`], created by [`Framed::split`].
+///
+/// Implements [`Sink`], using the `Encoder` of its own copy of the codec.
+#[derive(Debug)]
+pub struct WriteHalf<T, U> {
+    io: SharedIo<T>,
+
+    /// the codec used to encode frames
+    pub codec: U,
+
+    w_buffer: WriteBuffer,
+
+    /// The high-water mark for writes, in bytes
+    ///
+    /// see [`Framed::w_high_water_mark`]
+    pub w_high_water_mark: usize,
+
+    stats: StatsCell,
+}
+
+// the codecs are never pinned
+impl<T, U> Unpin for ReadHalf<T, U> {}
+impl<T, U> Unpin for WriteHalf<T, U> {}
+
+/// The error returned by [`ReadHalf::reunite`] if the halves
+/// didn't originate from the same [`Framed`].
+pub struct ReuniteError<T, U>(pub ReadHalf<T, U>, pub WriteHalf<T, U>);
+
+impl<T, U> fmt::Debug for ReuniteError<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReuniteError").finish()
+    }
+}
+
+impl<T, U> fmt::Display for ReuniteError<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("tried to reunite halves which aren't from the same Framed")
+    }
+}
+
+impl<T, U> std::error::Error for ReuniteError<T, U> {}
+
+impl<T, U> ReadHalf<T, U> {
+    /// Returns a reference to the read buffer.
+    pub fn read_buffer(&self) -> &BytesMut {
+        &self.r_buffer.data
+    }
+
+    /// Returns the state of the `Stream` part.
+    pub fn read_state(&self) -> ReadState {
+        self.r_buffer.state
+    }
+
+    /// Checks if this half and the given write half
+    /// originate from the same [`Framed`].
+    pub fn is_pair_of(&self, other: &WriteHalf<T, U>) -> bool {
+        Arc::ptr_eq(&self.io, &other.io)
+    }
+
+    /// Restores the original [`Framed`], including the contents of both buffers.
+    ///
+    /// The codec of the read half is kept, because it might contain
+    /// decoder state which belongs to the contents of the read buffer.
+    #[allow(clippy::result_large_err)]
+    pub fn reunite(self, other: WriteHalf<T, U>) -> Result<Framed<T, U>, ReuniteError<T, U>> {
+        if !self.is_pair_of(&other) {
+            return Err(ReuniteError(self, other));
+        }
+
+        let ReadHalf {
+            io,
+            codec,
+            r_buffer,
+            r_strategy,
+            r_max_buffer_size,
+            r_end_on_error,
+            stats: r_stats,
+        } = self;
+        let WriteHalf {
+            io: io2,
+            w_buffer,
+            w_high_water_mark,
+            stats: w_stats,
+            ..
+        } = other;
+        drop(io2);
+
+        let inner = Arc::try_unwrap(io)
+            .ok()
+            .expect("Framed: unique halves")
+            .into_inner()
+            .expect("Framed: shared I/O object poisoned");
+
+        Ok(Framed {
+            inner,
+            codec,
+            w_buffer,
+            w_high_water_mark,
+            r_buffer,
+            r_strategy,
+            r_max_buffer_size,
+            r_end_on_error,
+            stats: StatsCell::combine(r_stats, w_stats),
+        })
+    }
+}
+
+impl<T, U> WriteHalf<T, U> {
+    /// Checks if this half and the given read half
+    /// originate from the same [`Framed`].
+    pub fn is_pair_of(&self, other: &ReadHalf<T, U>) -> bool {
+        other.is_pair_of(self)
+    }
+
+    /// Restores the original [`Framed`], including the contents of both buffers.
+    ///
+    /// The codec of the read half is kept, see [`ReadHalf::reunite`].
+    #[allow(clippy::result_large_err)]
+    pub fn reunite(self, other: ReadHalf<T, U>) -> Result<Framed<T, U>, ReuniteError<T, U>> {
+        other.reunite(self)
+    }
+
+    fn poll_flush_until(
+        &mut self,
+        cx: &mut Context<'_>,
+        limit: usize,
+    ) -> Poll<Result<(), std::io::Error>>
+    where
+        T: AsyncWrite + Unpin,
+    {
+        write::poll_flush_until(
+            Pin::new(&mut *lock(&self.io)),
+            cx,
+            &mut self.w_buffer,
+            limit,
+            &mut self.stats,
+        )
+    }
+}
+
+impl<T: AsyncRead + Unpin, U: Decoder> Stream for ReadHalf<T, U> {
+    type Item = Result<U::Item, Error<U::Error>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let io = &this.io;
+        // decoding doesn't need the lock, only the reads do
+        read::next_frame_with(
+            &mut this.codec,
+            &mut this.r_buffer,
+            this.r_strategy,
+            this.r_max_buffer_size,
+            this.r_end_on_error,
+            &mut this.stats,
+            |buf| Pin::new(&mut *lock(io)).poll_read(cx, buf),
+        )
+    }
+}
+
+impl<T: AsyncRead + Unpin, U: Decoder> FusedStream for ReadHalf<T, U> {
+    fn is_terminated(&self) -> bool {
+        self.r_buffer.is_terminated()
+    }
+}
+
+impl<T, U> FlushSink for WriteHalf<T, U>
+where
+    T: AsyncWrite + Unpin,
+    U: EncoderError,
+{
+    type Error = Error<U::Error>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        let high_water_mark = this.w_high_water_mark - 1;
+        this.poll_flush_until(cx, high_water_mark)
+            .map_err(Into::into)
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_flush_until(cx, 0).map_err(Into::into)
+    }
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_flush_until(cx, 0))?;
+        Pin::new(&mut *lock(&this.io))
+            .poll_close(cx)
+            .map_err(Into::into)
+    }
+}
+
+impl<'a, Item, T, U> Sink<&'a Item> for WriteHalf<T, U>
+where
+    Item: ?Sized,
+    T: AsyncWrite + Unpin,
+    U: Encoder<Item>,
+{
+    fn start_send(self: Pin<&mut Self>, item: &'a Item) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        this.codec
+            .encode_vectored(item, &mut this.w_buffer)
+            .map_err(Error::Codec)?;
+        this.stats.on_encode(this.w_buffer.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+/// Traffic statistics of framed transports,
+/// see [`Framed::stats`](crate::Framed::stats).
+#[cfg(feature = "stats")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// the amount of frames decoded by the `Stream` part
+    pub frames_decoded: u64,
+    /// the amount of frames encoded by the `Sink` part
+    pub frames_encoded: u64,
+    /// the amount of bytes read from the underlying I/O object
+    pub bytes_read: u64,
+    /// the amount of bytes written to the underlying I/O object
+    pub bytes_written: u64,
+    /// the amount of flushes of the underlying I/O object
+    pub flushes: u64,
+    /// the peak size of the read buffer, in bytes
+    pub peak_read_buffer: usize,
+    /// the peak size of the write buffer, in bytes
+    pub peak_write_buffer: usize,
+}
+
+/// Collects [`Stats`] if the `stats` feature is enabled,
+/// and is zero-sized otherwise.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StatsCell {
+    #[cfg(feature = "stats")]
+    inner: Stats,
+}
+
+#[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+impl StatsCell {
+    #[cfg(feature = "stats")]
+    pub(crate) fn get(&self) -> &Stats {
+        &self.inner
+    }
+
+    /// combines the read statistics of `read` with the write statistics of `write`
+    #[cfg(feature = "stats")]
+    pub(crate) fn combine(read: Self, write: Self) -> Self {
+        let (read, write) = (read.inner, write.inner);
+        Self {
+            inner: Stats {
+                frames_encoded: write.frames_encoded,
+                bytes_written: write.bytes_written,
+                flushes: write.flushes,
+                peak_write_buffer: write.peak_write_buffer,
+                ..read
+            },
+        }
+    }
+
+    #[cfg(not(feature = "stats"))]
+    pub(crate) fn combine(_: Self, _: Self) -> Self {
+        Self {}
+    }
+
+    #[inline]
+    pub(crate) fn on_decode(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.inner.frames_decoded += 1;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn on_read(&mut self, num_read: usize, buffered: usize) {
+        #[cfg(feature = "stats")]
+        {
+            self.inner.bytes_read += num_read as u64;
+            self.inner.peak_read_buffer = std::cmp::max(self.inner.peak_read_buffer, buffered);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn on_encode(&mut self, buffered: usize) {
+        #[cfg(feature = "stats")]
+        {
+            self.inner.frames_encoded += 1;
+            self.inner.peak_write_buffer = std::cmp::max(self.inner.peak_write_buffer, buffered);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn on_write(&mut self, num_write: usize) {
+        #[cfg(feature = "stats")]
+        {
+            self.inner.bytes_written += num_write as u64;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn on_flush(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.inner.flushes += 1;
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! A conformance test kit for codecs.
+//!
+//! The functions in this module check that a codec behaves correctly
+//! when used with [`Framed`](crate::Framed), and panic with a descriptive
+//! message otherwise, so that they can be used directly in tests.
+//!
+//! The codecs must be able to decode what they encode, which means that they
+//! must produce self-delimiting frames (e.g. [`BytesCodec`](crate::codec::BytesCodec)
+//! can't be checked this way). The codecs are cloned before each check,
+//! so that state left over from one check doesn't affect the next one.
+//!
+//! ```
+//! use yz_futures_codec::{codec::Lines, testing};
+//!
+//! testing::check(Lines, &["hello\n", "\n", "world\n"]);
+//! ```
+
+use crate::codec::{Decoder, DecoderWithSkipAhead, Encoder, Limit, LimitError};
+use bytes::BytesMut;
+use std::fmt::Debug;
+
+fn encode_all<C, Item>(codec: &mut C, items: &[Item]) -> BytesMut
+where
+    C: Encoder<Item>,
+{
+    let mut buf = BytesMut::new();
+    for (n, item) in items.iter().enumerate() {
+        if let Err(e) = codec.encode(item, &mut buf) {
+            panic!("failed to encode item #{}: {}", n, e);
+        }
+    }
+    buf
+}
+
+/// decodes all frames in `buf` like `Framed` does, with `eof` marking the end of input
+fn decode_available<C: Decoder>(codec: &mut C, buf: &mut BytesMut, eof: bool) -> Vec<C::Item> {
+    let mut ret = Vec::new();
+    loop {
+        match codec.decode(buf) {
+            Ok(Some(item)) => ret.push(item),
+            Ok(None) if !eof || buf.is_empty() => return ret,
+            Ok(None) => match codec.decode_eof(buf) {
+                Ok(Some(item)) => ret.push(item),
+                Ok(None) => return ret,
+                Err(e) => panic!("failed to decode item #{} at EOF: {}", ret.len(), e),
+            },
+            Err(e) => panic!("failed to decode item #{}: {}", ret.len(), e),
+        }
+    }
+}
+
+fn assert_items<D, Item>(decoded: &[D], items: &[Item], ctx: &dyn Fn() -> String)
+where
+    D: PartialEq<Item> + Debug,
+    Item: Debug,
+{
+    assert_eq!(
+        decoded.len(),
+        items.len(),
+        "{}: decoded {:?}, expected {:?}",
+        ctx(),
+        decoded,
+        items
+    );
+    for (n, (d, i)) in decoded.iter().zip(items).enumerate() {
+        assert!(
+            d == i,
+            "{}: item #{} was decoded as {:?}, expected {:?}",
+            ctx(),
+            n,
+            d,
+            i
+        );
+    }
+}
+
+/// Runs [`roundtrip`], [`split_at_every_boundary`] and [`decode_eof`].
+pub fn check<C, Item>(codec: C, items: &[Item])
+where
+    C: Encoder<Item> + Decoder + Clone,
+    C::Item: PartialEq<Item> + Debug,
+    Item: Debug,
+{
+    roundtrip(codec.clone(), items);
+    split_at_every_boundary(codec.clone(), items);
+    decode_eof(codec, items);
+}
+
+/// Checks that the encoded `items` are decoded again if the whole buffer is available at once.
+pub fn roundtrip<C, Item>(mut codec: C, items: &[Item])
+where
+    C: Encoder<Item> + Decoder,
+    C::Item: PartialEq<Item> + Debug,
+    Item: Debug,
+{
+    let mut buf = encode_all(&mut codec, items);
+    let decoded = decode_available(&mut codec, &mut buf, false);
+    assert_items(&decoded, items, &|| "roundtrip".to_string());
+    assert!(buf.is_empty(), "roundtrip: {} bytes left over", buf.len());
+}
+
+/// Checks that the encoded `items` are decoded correctly if the input is split
+/// into two reads at every possible position, and if it arrives one byte at a time.
+pub fn split_at_every_boundary<C, Item>(mut codec: C, items: &[Item])
+where
+    C: Encoder<Item> + Decoder + Clone,
+    C::Item: PartialEq<Item> + Debug,
+    Item: Debug,
+{
+    let encoded = encode_all(&mut codec.clone(), items);
+
+    for at in 0..=encoded.len() {
+        let mut codec = codec.clone();
+        let mut buf = BytesMut::from(&encoded[..at]);
+        let mut decoded = decode_available(&mut codec, &mut buf, false);
+        buf.extend_from_slice(&encoded[at..]);
+        decoded.extend(decode_available(&mut codec, &mut buf, false));
+        let ctx = || format!("split at byte {}", at);
+        assert_items(&decoded, items, &ctx);
+        assert!(buf.is_empty(), "{}: {} bytes left over", ctx(), buf.len());
+    }
+
+    let mut buf = BytesMut::new();
+    let mut decoded = Vec::new();
+    for &byte in &encoded[..] {
+        buf.extend_from_slice(&[byte]);
+        decoded.extend(decode_available(&mut codec, &mut buf, false));
+    }
+    let ctx = || "one byte at a time".to_string();
+    assert_items(&decoded, items, &ctx);
+    assert!(buf.is_empty(), "{}: {} bytes left over", ctx(), buf.len());
+}
+
+/// Checks the behaviour of the decoder at EOF:
+///
+/// * the encoded `items` are decoded correctly if EOF is reached directly after them,
+/// * `decode_eof` returns `None` if the buffer is empty,
+/// * if the last byte is missing, all items except the last one are still decoded.
+pub fn decode_eof<C, Item>(codec: C, items: &[Item])
+where
+    C: Encoder<Item> + Decoder + Clone,
+    C::Item: PartialEq<Item> + Debug,
+    Item: Debug,
+{
+    let mut encoded = encode_all(&mut codec.clone(), items);
+
+    let mut dec = codec.clone();
+    let mut buf = encoded.clone();
+    let decoded = decode_available(&mut dec, &mut buf, true);
+    assert_items(&decoded, items, &|| "decode at EOF".to_string());
+    assert!(
+        buf.is_empty(),
+        "decode at EOF: {} bytes left over",
+        buf.len()
+    );
+    match dec.decode_eof(&mut buf) {
+        Ok(None) => {}
+        x => panic!("decode_eof on an empty buffer returned {:?}", x),
+    }
+
+    if items.is_empty() || encoded.is_empty() {
+        return;
+    }
+    encoded.truncate(encoded.len() - 1);
+    let mut dec = codec;
+    let mut decoded = Vec::new();
+    while let Ok(Some(item)) = dec.decode(&mut encoded) {
+        decoded.push(item);
+    }
+    let (_, items) = items.split_last().unwrap();
+    assert!(
+        decoded.len() >= items.len(),
+        "truncated input: decoded {:?}, expected at least {:?}",
+        decoded,
+        items
+    );
+    assert_items(&decoded[..items.len()], items, &|| {
+        "truncated input".to_string()
+    });
+}
+
+/// Checks that [`Limit`] recovers from the oversized item `big` via skip-ahead.
+///
+/// `big` must encode to a frame larger than `max_frame_size`,
+/// and `small` must encode to a frame which fits into it.
+/// The encoded items are fed to the decoder one byte at a time.
+pub fn limit_recovery<C, Item>(codec: C, big: &Item, small: &Item, max_frame_size: usize)
+where
+    C: Encoder<Item> + DecoderWithSkipAhead + Clone,
+    <C as Decoder>::Item: PartialEq<Item> + Debug,
+    Item: Debug,
+{
+    let mut enc = codec.clone();
+    let big_len = encode_all(&mut enc, std::slice::from_ref(big)).len();
+    assert!(
+        big_len > max_frame_size,
+        "the big item is only {} bytes long",
+        big_len
+    );
+    let mut encoded = encode_all(&mut enc, std::slice::from_ref(big));
+    encoded.extend_from_slice(&encode_all(&mut enc, std::slice::from_ref(small)));
+    encoded.extend_from_slice(&encode_all(&mut enc, std::slice::from_ref(small)));
+
+    let mut dec = Limit::new(codec, max_frame_size);
+    let mut buf = BytesMut::new();
+    let (mut exceeded, mut decoded) = (0, Vec::new());
+    for &byte in &encoded[..] {
+        buf.extend_from_slice(&[byte]);
+        loop {
+            match dec.decode(&mut buf) {
+                Ok(Some(item)) => decoded.push(item),
+                Ok(None) => break,
+                Err(LimitError::LimitExceeded(_)) => exceeded += 1,
+                Err(e) => panic!("limit recovery: unexpected error: {}", e),
+            }
+        }
+    }
+    assert_eq!(exceeded, 1, "limit recovery: expected exactly one error");
+    assert!(
+        decoded.len() == 2 && decoded.iter().all(|d| d == small),
+        "limit recovery: decoded {:?}, expected {:?} twice",
+        decoded,
+        small
+    );
+    assert!(
+        buf.is_empty(),
+        "limit recovery: {} bytes left over",
+        buf.len()
+    );
+}
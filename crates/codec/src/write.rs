@@ -0,0 +1,327 @@
+use crate::codec::{Encoder, EncoderError};
+use crate::read::INITIAL_CAPACITY;
+use crate::stats::StatsCell;
+use crate::Error;
+use bytes::{Buf, Bytes, BytesMut};
+use futures_core::ready;
+use futures_io::AsyncWrite;
+use std::collections::VecDeque;
+use std::io::IoSlice;
+use std::task::{Context, Poll};
+use std::{io, ops::Deref, pin::Pin};
+use yz_futures_sink::{FlushSink, Sink};
+
+/// 2^17 bytes, which is slightly over 60% of the default
+/// TCP send buffer size (SO_SNDBUF)
+pub(crate) const DEFAULT_HIGH_WATER_MARK: usize = 131072;
+
+/// payloads smaller than this are copied into the write buffer,
+/// instead of being queued as separate chunks
+const MAX_COPY_SIZE: usize = 1024;
+
+/// the maximum amount of chunks passed to a single `poll_write_vectored` call
+const MAX_IO_SLICES: usize = 64;
+
+/// The write buffer of framed writers.
+///
+/// It consists of a queue of shared [`Bytes`] chunks, followed by a
+/// [`BytesMut`] buffer. Encoders can append small parts (e.g. headers)
+/// to the buffer, and large payloads as shared chunks, which avoids
+/// copying them. The whole buffer is written out via `poll_write_vectored`.
+#[derive(Debug, Default)]
+pub struct WriteBuffer {
+    chunks: VecDeque<Bytes>,
+    chunks_len: usize,
+    buf: BytesMut,
+}
+
+impl From<BytesMut> for WriteBuffer {
+    fn from(buf: BytesMut) -> Self {
+        Self {
+            buf,
+            ..Default::default()
+        }
+    }
+}
+
+impl WriteBuffer {
+    /// Creates an empty `WriteBuffer`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        BytesMut::with_capacity(capacity).into()
+    }
+
+    /// Returns the total amount of buffered bytes.
+    pub fn len(&self) -> usize {
+        self.chunks_len + self.buf.len()
+    }
+
+    /// Returns `true` if no bytes are buffered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the buffer at the end of the queue,
+    /// which can be used to append data by copying it.
+    pub fn buffer_mut(&mut self) -> &mut BytesMut {
+        &mut self.buf
+    }
+
+    /// Appends the given slice by copying it.
+    pub fn extend_from_slice(&mut self, src: &[u8]) {
+        self.buf.extend_from_slice(src);
+    }
+
+    /// Appends the given shared chunk, without copying it
+    /// (unless it is small enough that copying is cheaper).
+    pub fn push_bytes(&mut self, src: Bytes) {
+        if src.len() < MAX_COPY_SIZE {
+            self.buf.extend_from_slice(&src);
+        } else {
+            if !self.buf.is_empty() {
+                let buf = self.buf.split().freeze();
+                self.chunks_len += buf.len();
+                self.chunks.push_back(buf);
+            }
+            self.chunks_len += src.len();
+            self.chunks.push_back(src);
+        }
+    }
+
+    /// Shortens the buffer to `len` bytes, discarding the remaining bytes at the end.
+    ///
+    /// If `len` is greater than the current length, this has no effect.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.chunks_len {
+            self.buf.truncate(len - self.chunks_len);
+            return;
+        }
+        self.buf.clear();
+        while let Some(mut chunk) = self.chunks.pop_back() {
+            self.chunks_len -= chunk.len();
+            if len > self.chunks_len {
+                chunk.truncate(len - self.chunks_len);
+                self.chunks_len += chunk.len();
+                self.chunks.push_back(chunk);
+                break;
+            }
+        }
+    }
+
+    /// Returns an iterator over the buffered chunks, in order.
+    pub fn chunks(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.chunks
+            .iter()
+            .map(|i| &i[..])
+            .chain(std::iter::once(&self.buf[..]))
+            .filter(|i| !i.is_empty())
+    }
+
+    /// Passes the buffered chunks to `write`, and discards the bytes written by it.
+    pub(crate) fn write_with<F>(&mut self, stats: &mut StatsCell, write: F) -> Poll<io::Result<()>>
+    where
+        F: FnOnce(&[IoSlice<'_>]) -> Poll<io::Result<usize>>,
+    {
+        let num_write = {
+            let mut slices = [IoSlice::new(&[]); MAX_IO_SLICES];
+            let mut cnt = 0;
+            for (slot, chunk) in slices.iter_mut().zip(self.chunks()) {
+                *slot = IoSlice::new(chunk);
+                cnt += 1;
+            }
+            ready!(write(&slices[..cnt]))?
+        };
+
+        if num_write == 0 {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "FramedWrite: end of input",
+            )));
+        }
+
+        self.advance(num_write);
+        stats.on_write(num_write);
+        Poll::Ready(Ok(()))
+    }
+
+    /// Discards the first `cnt` bytes.
+    fn advance(&mut self, mut cnt: usize) {
+        while let Some(chunk) = self.chunks.front_mut() {
+            if cnt < chunk.len() {
+                chunk.advance(cnt);
+                self.chunks_len -= cnt;
+                return;
+            }
+            cnt -= chunk.len();
+            self.chunks_len -= chunk.len();
+            self.chunks.pop_front();
+        }
+        self.buf.advance(cnt);
+    }
+}
+
+/// writes out `w_buffer` until at most `limit` bytes remain in it
+pub(crate) fn poll_flush_until<T>(
+    mut inner: Pin<&mut T>,
+    cx: &mut Context<'_>,
+    w_buffer: &mut WriteBuffer,
+    limit: usize,
+    stats: &mut StatsCell,
+) -> Poll<Result<(), io::Error>>
+where
+    T: AsyncWrite + ?Sized,
+{
+    let orig_len = w_buffer.len();
+
+    while w_buffer.len() > limit {
+        ready!(w_buffer.write_with(stats, |slices| inner
+            .as_mut()
+            .poll_write_vectored(cx, slices)))?;
+    }
+
+    if orig_len != w_buffer.len() {
+        ready!(inner.poll_flush(cx))?;
+        stats.on_flush();
+    }
+    Poll::Ready(Ok(()))
+}
+
+/// A `Sink` of frames encoded into an underlying [`AsyncWrite`],
+/// using the `Encoder` trait.
+///
+/// This is the write-only counterpart of [`Framed`](crate::Framed).
+///
+/// # Example
+/// ```
+/// # futures_lite::future::block_on(async move {
+/// use yz_futures_codec::{codec::Lines, FramedWrite, Error};
+/// use yz_futures_util::sink::SinkExt;
+///
+/// let mut framed = FramedWrite::new(Vec::new(), Lines);
+/// framed.send_unpin("hello\n").await?;
+/// assert_eq!(framed.into_inner(), b"hello\n");
+/// # Ok::<_, Error<_>>(())
+/// # }).unwrap();
+/// ```
+#[pin_project::pin_project]
+#[derive(Debug)]
+pub struct FramedWrite<T, E> {
+    #[pin]
+    inner: T,
+
+    /// the codec used to encode frames
+    pub codec: E,
+
+    w_buffer: WriteBuffer,
+
+    /// The high-water mark for writes, in bytes
+    ///
+    /// see [`Framed::w_high_water_mark`](crate::Framed::w_high_water_mark)
+    pub w_high_water_mark: usize,
+
+    stats: StatsCell,
+}
+
+impl<T, E> Deref for FramedWrite<T, E> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T, E> FramedWrite<T, E> {
+    /// Creates a new `FramedWrite` with the given encoder.
+    pub fn new(inner: T, codec: E) -> Self {
+        Self {
+            inner,
+            codec,
+            w_buffer: WriteBuffer::with_capacity(INITIAL_CAPACITY),
+            w_high_water_mark: DEFAULT_HIGH_WATER_MARK,
+            stats: StatsCell::default(),
+        }
+    }
+
+    /// Release the I/O and Codec
+    pub fn release(self) -> (T, E) {
+        (self.inner, self.codec)
+    }
+
+    /// Consumes the `FramedWrite`, returning its underlying I/O stream.
+    ///
+    /// Note that any data which wasn't flushed yet is lost.
+    pub fn into_inner(self) -> T {
+        self.release().0
+    }
+
+    /// Returns a mutable reference to the underlying I/O stream.
+    ///
+    /// Note that care should be taken to not tamper with the underlying stream
+    /// of data going out as it may corrupt the stream of frames otherwise
+    /// being worked with.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Returns a reference to the write buffer.
+    pub fn write_buffer(&self) -> &WriteBuffer {
+        &self.w_buffer
+    }
+
+    /// Returns the traffic statistics of this `FramedWrite`.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> &crate::Stats {
+        self.stats.get()
+    }
+}
+
+impl<T: AsyncWrite, E> FramedWrite<T, E> {
+    fn poll_flush_until(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        limit: usize,
+    ) -> Poll<Result<(), io::Error>> {
+        let this = self.project();
+        poll_flush_until(this.inner, cx, this.w_buffer, limit, this.stats)
+    }
+}
+
+impl<T, E> FlushSink for FramedWrite<T, E>
+where
+    T: AsyncWrite,
+    E: EncoderError,
+{
+    type Error = Error<E::Error>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let high_water_mark = self.w_high_water_mark - 1;
+        self.poll_flush_until(cx, high_water_mark)
+            .map_err(Into::into)
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush_until(cx, 0).map_err(Into::into)
+    }
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        self.project().inner.poll_close(cx).map_err(Into::into)
+    }
+}
+
+impl<'a, Item, T, E> Sink<&'a Item> for FramedWrite<T, E>
+where
+    Item: ?Sized,
+    T: AsyncWrite,
+    E: Encoder<Item>,
+{
+    fn start_send(self: Pin<&mut Self>, item: &'a Item) -> Result<(), Self::Error> {
+        let this = self.project();
+        this.codec
+            .encode_vectored(item, this.w_buffer)
+            .map_err(Error::Codec)?;
+        this.stats.on_encode(this.w_buffer.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::{self, Cursor, Read};
+use yz_futures_codec::{
+    codec::{Length, Limit, LimitError, Lines},
+    Error, FramedSync, ReadState,
+};
+
+// Returns at most one byte per read, interrupting every other read
+struct OneByteAtATime<'a> {
+    data: &'a [u8],
+    interrupt: bool,
+}
+impl Read for OneByteAtATime<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        let n = std::cmp::min(1, std::cmp::min(buf.len(), self.data.len()));
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn lines_roundtrip() {
+    let mut framed = FramedSync::new(Cursor::new(Vec::new()), Lines);
+    framed.feed("hello\n").unwrap();
+    assert_eq!(framed.write_buffer().len(), 6);
+    framed.send("world\n").unwrap();
+    assert!(framed.write_buffer().is_empty());
+
+    framed.inner_mut().set_position(0);
+    let lines: Vec<_> = framed.by_ref().collect::<Result<_, _>>().unwrap();
+    assert_eq!(lines, ["hello\n", "world\n"]);
+    assert_eq!(framed.read_state(), ReadState::Eof);
+}
+
+#[test]
+fn bytes_remaining_in_stream() {
+    let io = OneByteAtATime {
+        data: b"one\ntw",
+        interrupt: false,
+    };
+    let mut framed = FramedSync::new(io, Lines);
+    assert_eq!(framed.next().unwrap().unwrap(), "one\n");
+    match framed.next() {
+        Some(Err(Error::Io(e))) => {
+            assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+            assert_eq!(e.to_string(), "bytes remaining in stream");
+        }
+        x => panic!("unexpected result: {:?}", x),
+    }
+    assert!(framed.next().is_none());
+    assert_eq!(framed.read_state(), ReadState::Failed);
+}
+
+#[test]
+fn limit_recovers() {
+    let mut framed = FramedSync::new(
+        Cursor::new(b"\x08abcdefgh\x02ok".to_vec()),
+        Limit::new(Length::<u8>::new(), 4),
+    );
+    framed.r_strategy = yz_futures_codec::ReadStrategy::Exact(4);
+    assert!(matches!(
+        framed.next(),
+        Some(Err(Error::Codec(LimitError::LimitExceeded(_))))
+    ));
+    assert_eq!(&framed.next().unwrap().unwrap()[..], b"ok");
+    assert!(framed.next().is_none());
+}
@@ -5,7 +5,7 @@ use yz_futures_codec::{codec::BytesCodec, Framed};
 #[test]
 fn decodes() {
     let mut buf = [0u8; 32];
-    let expected = buf.clone();
+    let expected = buf;
     let cur = Cursor::new(&mut buf[..]);
     let mut framed = Framed::new(cur, BytesCodec {});
 
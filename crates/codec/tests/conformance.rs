@@ -0,0 +1,134 @@
+#![cfg(feature = "testing")]
+
+use bytes::Bytes;
+use yz_futures_codec::codec::{Length, Lines, LinesCodec, Varint};
+use yz_futures_codec::testing;
+
+fn payloads() -> Vec<Bytes> {
+    vec![
+        Bytes::from_static(b"hello"),
+        Bytes::new(),
+        Bytes::from(vec![0xffu8; 300]),
+    ]
+}
+
+#[test]
+fn length() {
+    testing::check(Length::<u16>::new(), &payloads());
+    testing::check(Length::<u32>::new(), &payloads());
+    testing::check(Length::<u64>::new(), &payloads());
+    testing::check(Length::<Varint>::new(), &payloads());
+    testing::limit_recovery(
+        Length::<u16>::new(),
+        &Bytes::from(vec![1u8; 100]),
+        &Bytes::from_static(b"ok"),
+        16,
+    );
+    testing::limit_recovery(
+        Length::<Varint>::new(),
+        &Bytes::from(vec![1u8; 200]),
+        &Bytes::from_static(b"ok"),
+        16,
+    );
+}
+
+#[test]
+fn lines() {
+    testing::check(Lines, &["hello\n", "\n", "world\n"]);
+    testing::check(LinesCodec::new(), &["hello\n", "\n", "world\r\n"]);
+    testing::check(
+        LinesCodec::new()
+            .with_strip_terminator(true)
+            .with_append_terminator(true)
+            .with_crlf(true),
+        &["hello", "", "world\r"],
+    );
+    testing::limit_recovery(Lines, &"too long\n", &"ok\n", 4);
+    testing::limit_recovery(
+        LinesCodec::new()
+            .with_strip_terminator(true)
+            .with_append_terminator(true),
+        &"too long",
+        &"ok",
+        4,
+    );
+}
+
+#[cfg(feature = "json")]
+mod json {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use yz_futures_codec::codec::{Framing, Json, JsonFormat, NdJson};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Item {
+        name: String,
+        data: Vec<u16>,
+    }
+
+    fn items() -> Vec<Item> {
+        vec![
+            Item {
+                name: "first".to_string(),
+                data: vec![1, 2, 3],
+            },
+            Item {
+                name: "{\"}".to_string(),
+                data: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn json() {
+        testing::check(Json::<Item, Item>::new(), &items());
+    }
+
+    #[test]
+    fn json_formats() {
+        for format in [
+            JsonFormat::NewlineSeparated,
+            JsonFormat::Pretty,
+            JsonFormat::Seq,
+        ] {
+            testing::check(Json::<Item, Item>::new().with_format(format), &items());
+        }
+    }
+
+    #[test]
+    fn ndjson() {
+        testing::check(NdJson::<Item, Item>::new(), &items());
+        let big = Item {
+            name: "x".repeat(64),
+            data: vec![],
+        };
+        testing::limit_recovery(NdJson::<Item, Item>::new(), &big, &items().remove(0), 48);
+    }
+
+    #[test]
+    fn json_framed() {
+        let codec = Framing::new(Length::<u32>::new(), Json::<Item, Item>::new());
+        testing::check(codec.clone(), &items());
+        let big = Item {
+            name: "x".repeat(64),
+            data: vec![],
+        };
+        testing::limit_recovery(codec, &big, &items().remove(0), 48);
+    }
+}
+
+#[cfg(feature = "cbor")]
+mod cbor {
+    use super::*;
+    use yz_futures_codec::codec::{Cbor, Framing};
+
+    #[test]
+    fn cbor() {
+        let items = vec!["first".to_string(), String::new(), "x".repeat(300)];
+        testing::check(Cbor::<String, String>::new(), &items);
+        testing::check(
+            Framing::new(Length::<u16>::new(), Cbor::<String, String>::new()),
+            &items,
+        );
+    }
+}
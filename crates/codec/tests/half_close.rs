@@ -0,0 +1,92 @@
+use futures_lite::future::block_on;
+use futures_util::{
+    future::poll_fn,
+    io::{AsyncRead, AsyncWrite},
+    stream::TryStreamExt,
+};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use yz_futures_codec::{codec::Lines, Framed};
+use yz_futures_util::sink::SinkExt;
+
+// Reads the given input, records writes and whether the write direction was closed
+struct HalfDuplex {
+    input: &'static [u8],
+    written: Vec<u8>,
+    write_closed: bool,
+}
+impl AsyncRead for HalfDuplex {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let n = std::cmp::min(buf.len(), self.input.len());
+        buf[..n].copy_from_slice(&self.input[..n]);
+        self.input = &self.input[n..];
+        Poll::Ready(Ok(n))
+    }
+}
+impl AsyncWrite for HalfDuplex {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        assert!(!self.write_closed);
+        self.written.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.write_closed = true;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn write_after_read_eof() {
+    let io = HalfDuplex {
+        input: b"req1\nreq2\n",
+        written: Vec::new(),
+        write_closed: false,
+    };
+    let mut framed = Framed::new(io, Lines);
+
+    let mut requests = Vec::new();
+    while let Some(req) = block_on(framed.try_next()).unwrap() {
+        requests.push(req);
+    }
+    assert_eq!(requests, ["req1\n", "req2\n"]);
+    assert!(framed.is_read_closed());
+
+    for req in requests {
+        block_on(framed.send_unpin(&format!("re: {}", req))).unwrap();
+    }
+    block_on(poll_fn(|cx| Pin::new(&mut framed).poll_close_write(cx))).unwrap();
+
+    assert!(framed.write_closed);
+    assert_eq!(framed.written, b"re: req1\nre: req2\n");
+}
+
+#[test]
+fn read_after_write_close() {
+    let io = HalfDuplex {
+        input: b"late\n",
+        written: Vec::new(),
+        write_closed: false,
+    };
+    let mut framed = Framed::new(io, Lines);
+    block_on(framed.send_unpin("bye\n")).unwrap();
+    block_on(poll_fn(|cx| Pin::new(&mut framed).poll_close_write(cx))).unwrap();
+    assert!(!framed.is_read_closed());
+
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(next, "late\n");
+    assert!(block_on(framed.try_next()).unwrap().is_none());
+}
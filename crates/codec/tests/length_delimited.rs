@@ -1,6 +1,9 @@
 use futures_lite::future::block_on;
 use futures_util::{io::Cursor, stream::StreamExt};
-use yz_futures_codec::{codec::Length, Framed};
+use yz_futures_codec::{
+    codec::{Length, LengthError, Varint},
+    Error, Framed,
+};
 use yz_futures_util::sink::SinkExt;
 
 #[test]
@@ -28,3 +31,16 @@ fn same_msgs_are_received_as_were_sent() {
 
     assert!(msgs == vec!["msg1", "msg2", "msg3"]);
 }
+
+#[test]
+fn recovers_from_invalid_header() {
+    let cur = Cursor::new(b"\x80\x00\x05hello".to_vec());
+    let mut framed = Framed::new(cur, Length::<Varint>::new());
+
+    assert!(matches!(
+        block_on(framed.next()),
+        Some(Err(Error::Codec(LengthError::Overlong)))
+    ));
+    assert_eq!(&block_on(framed.next()).unwrap().unwrap()[..], b"hello");
+    assert!(block_on(framed.next()).is_none());
+}
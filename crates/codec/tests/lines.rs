@@ -1,6 +1,16 @@
 use futures_lite::future::block_on;
-use futures_util::{io::Cursor, stream::TryStreamExt};
-use yz_futures_codec::{codec::Lines, Framed};
+use futures_util::{
+    io::Cursor,
+    stream::{StreamExt, TryStreamExt},
+};
+use yz_futures_codec::{
+    codec::{
+        ByteLinesCodec, EofPolicy, Limit, LimitError, Lines, LinesCodec, LinesCodecError,
+        Utf8Policy,
+    },
+    Error, Framed, ReadStrategy,
+};
+use yz_futures_util::sink::SinkExt;
 
 #[test]
 fn it_works() {
@@ -15,3 +25,156 @@ fn it_works() {
 
     assert!(block_on(framed.try_next()).is_err());
 }
+
+#[test]
+fn unterminated_line_fails_by_default() {
+    let mut framed = Framed::new(Cursor::new("Hello\nWorld"), LinesCodec::new());
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(next, "Hello\n");
+    match block_on(framed.try_next()) {
+        Err(Error::Io(e)) => assert_eq!(e.to_string(), "bytes remaining in stream"),
+        x => panic!("unexpected result: {:?}", x),
+    }
+}
+
+#[test]
+fn unterminated_line_returned() {
+    let codec = LinesCodec::new().with_eof_policy(EofPolicy::Return);
+    let framed = Framed::new(Cursor::new("Hello\nWorld"), codec);
+    let lines: Vec<_> = block_on(framed.try_collect()).unwrap();
+    assert_eq!(lines, ["Hello\n", "World"]);
+}
+
+#[test]
+fn unterminated_line_dropped() {
+    let codec = LinesCodec::new().with_eof_policy(EofPolicy::Drop);
+    let framed = Framed::new(Cursor::new("Hello\nWorld"), codec);
+    let lines: Vec<_> = block_on(framed.try_collect()).unwrap();
+    assert_eq!(lines, ["Hello\n"]);
+}
+
+#[test]
+fn strip_terminator_and_crlf() {
+    let input = "a\r\nb\nc\r\r\n";
+    let decode = |codec: LinesCodec| -> Vec<String> {
+        block_on(Framed::new(Cursor::new(input), codec).try_collect()).unwrap()
+    };
+    assert_eq!(decode(LinesCodec::new()), ["a\r\n", "b\n", "c\r\r\n"]);
+    assert_eq!(
+        decode(LinesCodec::new().with_strip_terminator(true)),
+        ["a\r", "b", "c\r\r"]
+    );
+    assert_eq!(
+        decode(
+            LinesCodec::new()
+                .with_strip_terminator(true)
+                .with_crlf(true)
+        ),
+        ["a", "b", "c\r"]
+    );
+}
+
+#[test]
+fn max_length_discards_line() {
+    let input = "short\r\nthis is too long\nagain way too long\nok\n";
+    let codec = LinesCodec::new().with_crlf(true).with_max_length(5);
+    let mut framed = Framed::new(Cursor::new(input), codec);
+    // read in small chunks, so that the long lines don't arrive at once
+    framed.r_strategy = ReadStrategy::Exact(4);
+
+    let next = block_on(framed.try_next()).unwrap();
+    assert_eq!(next.as_deref(), Some("short\r\n"));
+    for _ in 0..2 {
+        assert!(matches!(
+            block_on(framed.try_next()),
+            Err(Error::Codec(LinesCodecError::MaxLengthExceeded))
+        ));
+    }
+    let next = block_on(framed.try_next()).unwrap();
+    assert_eq!(next.as_deref(), Some("ok\n"));
+    assert_eq!(block_on(framed.try_next()).unwrap(), None);
+    assert!(framed.read_buffer().len() < 8);
+}
+
+#[test]
+fn append_terminator_roundtrips() {
+    let codec = LinesCodec::new()
+        .with_strip_terminator(true)
+        .with_append_terminator(true)
+        .with_crlf(true);
+    let mut framed = Framed::new(Cursor::new(Vec::new()), codec);
+    block_on(framed.send_unpin("foo")).unwrap();
+    block_on(framed.send_unpin("bar\r")).unwrap();
+    assert!(matches!(
+        block_on(framed.send_unpin("foo\nbar")),
+        Err(Error::Codec(LinesCodecError::EmbeddedNewline))
+    ));
+    assert_eq!(framed.get_ref(), b"foo\r\nbar\r\r\n");
+
+    framed.inner_mut().set_position(0);
+    let lines: Vec<_> = block_on(framed.try_collect()).unwrap();
+    assert_eq!(lines, ["foo", "bar\r"]);
+}
+
+#[test]
+fn utf8_policies() {
+    let input: &[u8] = b"a\xffb\nok\n";
+    let decode = |policy| {
+        let codec = LinesCodec::new()
+            .with_strip_terminator(true)
+            .with_utf8_policy(policy);
+        let framed = Framed::new(Cursor::new(input), codec);
+        block_on(framed.into_stream().collect::<Vec<_>>())
+    };
+
+    let lines = decode(Utf8Policy::Fail);
+    assert!(matches!(
+        lines[0],
+        Err(Error::Codec(LinesCodecError::Utf8(_)))
+    ));
+    assert_eq!(lines[1].as_ref().unwrap(), "ok");
+    assert_eq!(lines.len(), 2);
+
+    let lines: Vec<_> = decode(Utf8Policy::Lossy)
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(lines, ["a\u{fffd}b", "ok"]);
+
+    let lines: Vec<_> = decode(Utf8Policy::Skip)
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(lines, ["ok"]);
+}
+
+#[test]
+fn byte_lines() {
+    let input: &[u8] = b"a\xffb\r\nok";
+    let codec = ByteLinesCodec::new(
+        LinesCodec::new()
+            .with_strip_terminator(true)
+            .with_crlf(true)
+            .with_eof_policy(EofPolicy::Return),
+    );
+    let framed = Framed::new(Cursor::new(input), codec);
+    let lines: Vec<_> = block_on(framed.try_collect()).unwrap();
+    assert_eq!(lines, [&b"a\xffb"[..], b"ok"]);
+}
+
+#[test]
+fn limit_recovers() {
+    let input = "short\nthis line is too long\nok\n";
+    let mut framed = Framed::new(Cursor::new(input), Limit::new(Lines, 8));
+    framed.r_strategy = ReadStrategy::Exact(4);
+
+    let next = block_on(framed.try_next()).unwrap();
+    assert_eq!(next.as_deref(), Some("short\n"));
+    assert!(matches!(
+        block_on(framed.try_next()),
+        Err(Error::Codec(LimitError::LimitExceeded(_)))
+    ));
+    let next = block_on(framed.try_next()).unwrap();
+    assert_eq!(next.as_deref(), Some("ok\n"));
+    assert_eq!(block_on(framed.try_next()).unwrap(), None);
+}
@@ -0,0 +1,120 @@
+use futures_lite::future::block_on;
+use futures_util::{future::poll_fn, io::Cursor, stream::TryStreamExt};
+use std::pin::Pin;
+use yz_futures_codec::{
+    codec::{Length, Lines},
+    Framed, FramedParts,
+};
+use yz_futures_util::sink::{FlushSink, Sink, SinkExt};
+
+#[test]
+fn upgrade_keeps_read_buffer() {
+    let mut input = b"UPGRADE\n".to_vec();
+    input.extend_from_slice(&[0, 0, 0, 3]);
+    input.extend_from_slice(b"abc");
+    let mut framed = Framed::new(Cursor::new(input), Lines);
+
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(next, "UPGRADE\n");
+
+    let parts = framed.into_parts();
+    assert_eq!(&parts.read_buf[..], b"\0\0\0\x03abc");
+    let mut framed = Framed::from_parts(FramedParts {
+        io: parts.io,
+        codec: Length::<u32>::new(),
+        read_buf: parts.read_buf,
+        write_buf: parts.write_buf,
+        w_high_water_mark: parts.w_high_water_mark,
+    });
+
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(&next[..], b"abc");
+    assert!(block_on(framed.try_next()).unwrap().is_none());
+}
+
+#[test]
+fn parts_keep_write_buffer() {
+    let mut framed = Framed::new(Cursor::new(Vec::<u8>::new()), Lines);
+    block_on(poll_fn(|cx| Pin::new(&mut framed).poll_ready(cx))).unwrap();
+    Pin::new(&mut framed).start_send("unflushed\n").unwrap();
+
+    let parts = framed.into_parts();
+    let chunks: Vec<_> = parts.write_buf.chunks().collect();
+    assert_eq!(chunks, [b"unflushed\n"]);
+    assert!(parts.io.get_ref().is_empty());
+
+    let mut framed = Framed::from_parts(parts);
+    block_on(framed.send_unpin("flushed\n")).unwrap();
+    assert_eq!(framed.get_ref(), b"unflushed\nflushed\n");
+}
+
+#[test]
+fn map_codec_keeps_buffers() {
+    let mut input = b"UPGRADE\n".to_vec();
+    input.extend_from_slice(&[2, b'h', b'i', 0]);
+    let mut framed = Framed::new(Cursor::new(input), Lines);
+
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(next, "UPGRADE\n");
+
+    let mut framed = framed.map_codec(|_| Length::<u8>::new());
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(&next[..], b"hi");
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert!(next.is_empty());
+}
+
+#[test]
+fn replace_codec_pinned() {
+    let mut framed = Box::pin(Framed::new(b"a\nb\n".as_ref(), Lines));
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(next, "a\n");
+    let old = framed.as_mut().replace_codec(Lines);
+    assert_eq!(old, Lines);
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(next, "b\n");
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn map_codec_lines_to_json() {
+    use yz_futures_codec::codec::Json;
+
+    #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct Msg {
+        id: u16,
+    }
+
+    let input = b"HELLO json\n{\"id\":1}{\"id\":2}".to_vec();
+    let mut framed = Framed::new(Cursor::new(input), Lines);
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(next, "HELLO json\n");
+
+    let mut framed = framed.map_codec(|_| Json::<Msg, Msg>::new());
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(next, Msg { id: 1 });
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(next, Msg { id: 2 });
+    assert!(block_on(framed.try_next()).unwrap().is_none());
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn map_codec_lines_to_cbor() {
+    use yz_futures_codec::codec::{Cbor, Encoder};
+    use yz_futures_codec::BytesMut;
+
+    let mut input = BytesMut::new();
+    input.extend_from_slice(b"HELLO cbor\n");
+    let mut codec = Cbor::<Vec<u32>, Vec<u32>>::new();
+    codec.encode(&vec![1, 2, 3], &mut input).unwrap();
+
+    let mut framed = Framed::new(Cursor::new(input.to_vec()), Lines);
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(next, "HELLO cbor\n");
+
+    let mut framed = framed.map_codec(|_| codec);
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(next, vec![1, 2, 3]);
+    assert!(block_on(framed.try_next()).unwrap().is_none());
+}
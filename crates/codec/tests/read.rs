@@ -1,9 +1,14 @@
 use futures_lite::future::block_on;
-use futures_util::{io::AsyncRead, stream::StreamExt};
+use futures_util::{
+    io::AsyncRead,
+    stream::{FusedStream, StreamExt},
+};
 use std::io;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use yz_futures_codec::{codec::Decoder, codec::Lines, BytesMut, Framed};
+use yz_futures_codec::{
+    codec::Decoder, codec::Lines, BytesMut, Error, Framed, FramedRead, ReadState, ReadStrategy,
+};
 
 // Sends two lines at once, then nothing else forever
 struct MockBurstySender {
@@ -15,7 +20,7 @@ impl AsyncRead for MockBurstySender {
         _cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        const MESSAGES: &'static [u8] = b"one\ntwo\n";
+        const MESSAGES: &[u8] = b"one\ntwo\n";
         if !self.sent && buf.len() >= MESSAGES.len() {
             self.sent = true;
             buf[0..MESSAGES.len()].clone_from_slice(MESSAGES);
@@ -84,3 +89,180 @@ fn read_few_messages() {
         assert_eq!(item, 'a');
     }
 }
+
+#[test]
+fn framed_read_few_messages() {
+    let string: &[u8] = b"aabbbabbbabbbabb";
+    let input = OneByteAtATime { input: string };
+    let mut framed = FramedRead::new(input, AllTheAs);
+    for _ in 0..5 {
+        let item = block_on(framed.next()).unwrap().unwrap();
+        assert_eq!(item, 'a');
+    }
+    assert!(block_on(framed.next()).is_none());
+}
+
+// Records the sizes of the buffers passed to `poll_read`
+struct RecordReadSizes<'a> {
+    input: &'a [u8],
+    sizes: Vec<usize>,
+}
+impl AsyncRead for RecordReadSizes<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.sizes.push(buf.len());
+        let n = std::cmp::min(buf.len(), self.input.len());
+        buf[..n].copy_from_slice(&self.input[..n]);
+        self.input = &self.input[n..];
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[test]
+fn read_strategy_exact() {
+    let input = RecordReadSizes {
+        input: b"Hello\nWorld\n",
+        sizes: Vec::new(),
+    };
+    let mut framed = Framed::new(input, Lines);
+    framed.r_strategy = ReadStrategy::Exact(4);
+    let next = block_on(framed.next()).unwrap().unwrap();
+    assert_eq!(next, "Hello\n");
+    let next = block_on(framed.next()).unwrap().unwrap();
+    assert_eq!(next, "World\n");
+    assert!(block_on(framed.next()).is_none());
+    assert_eq!(framed.sizes, [4, 4, 4, 4]);
+}
+
+#[test]
+fn read_strategy_adaptive() {
+    let line = [b'a'; 100];
+    let mut data = line.to_vec();
+    data.push(b'\n');
+    let input = RecordReadSizes {
+        input: &data,
+        sizes: Vec::new(),
+    };
+    let mut framed = Framed::new(input, Lines);
+    framed.r_strategy = ReadStrategy::Adaptive {
+        initial: 8,
+        max: 64,
+    };
+    let next = block_on(framed.next()).unwrap().unwrap();
+    assert_eq!(next.as_bytes(), &data[..]);
+    assert_eq!(framed.sizes, [8, 16, 32, 64]);
+}
+
+#[test]
+fn read_buffer_limit() {
+    let mut data = b"short\n".to_vec();
+    data.extend_from_slice(&[b'a'; 100]);
+    let input = RecordReadSizes {
+        input: &data,
+        sizes: Vec::new(),
+    };
+    let mut framed = Framed::new(input, Lines);
+    framed.r_max_buffer_size = Some(32);
+    let next = block_on(framed.next()).unwrap().unwrap();
+    assert_eq!(next, "short\n");
+    match block_on(framed.next()).unwrap() {
+        Err(Error::ReadBufferFull(32)) => {}
+        x => panic!("unexpected result: {:?}", x),
+    }
+    assert_eq!(framed.read_buffer().len(), 32);
+    assert_eq!(framed.sizes.iter().sum::<usize>(), 32 + "short\n".len());
+}
+
+// Returns the given chunks one at a time, an empty chunk signals EOF
+struct Chunks(Vec<&'static [u8]>);
+impl AsyncRead for Chunks {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let chunk = self.0.remove(0);
+        buf[..chunk.len()].copy_from_slice(chunk);
+        Poll::Ready(Ok(chunk.len()))
+    }
+}
+
+#[test]
+fn no_reads_after_eof() {
+    let input = Chunks(vec![b"a\nb\n", b"", b"c\n"]);
+    let mut framed = Framed::new(input, Lines);
+    assert_eq!(block_on(framed.next()).unwrap().unwrap(), "a\n");
+    assert_eq!(framed.read_state(), ReadState::Open);
+    assert_eq!(block_on(framed.next()).unwrap().unwrap(), "b\n");
+    assert!(block_on(framed.next()).is_none());
+    assert_eq!(framed.read_state(), ReadState::Eof);
+    assert!(framed.is_terminated());
+    assert!(block_on(framed.next()).is_none());
+    assert_eq!(framed.0.len(), 1);
+}
+
+#[test]
+fn codec_error_continues_by_default() {
+    let input = Chunks(vec![b"\xff\nok\n", b""]);
+    let mut framed = Framed::new(input, Lines);
+    assert!(matches!(
+        block_on(framed.next()),
+        Some(Err(Error::Codec(_)))
+    ));
+    assert!(!framed.is_terminated());
+    assert_eq!(block_on(framed.next()).unwrap().unwrap(), "ok\n");
+    assert!(block_on(framed.next()).is_none());
+}
+
+#[test]
+fn codec_error_ends_stream() {
+    let input = Chunks(vec![b"\xff\nok\n", b""]);
+    let mut framed = Framed::new(input, Lines);
+    framed.r_end_on_error = true;
+    assert!(matches!(
+        block_on(framed.next()),
+        Some(Err(Error::Codec(_)))
+    ));
+    assert_eq!(framed.read_state(), ReadState::Failed);
+    assert!(framed.is_terminated());
+    assert!(block_on(framed.next()).is_none());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_one_byte_at_a_time() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use yz_futures_codec::codec::Json;
+
+    static DESERIALIZED: AtomicUsize = AtomicUsize::new(0);
+
+    // counts how often serde_json is invoked
+    #[derive(Debug, PartialEq)]
+    struct Counted(serde_json::Value);
+    impl<'de> serde::Deserialize<'de> for Counted {
+        fn deserialize<D: serde::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+            DESERIALIZED.fetch_add(1, Ordering::SeqCst);
+            serde_json::Value::deserialize(de).map(Counted)
+        }
+    }
+
+    let input = br#"{"a": [1, 2, {"b": "}\"{"}], "c": null} ["x", -1.5e3] "str""#;
+    let input = OneByteAtATime { input };
+    let mut framed = Framed::new(input, Json::<(), Counted>::new());
+    let mut items = Vec::new();
+    while let Some(item) = block_on(framed.next()) {
+        items.push(item.unwrap().0);
+    }
+    assert_eq!(
+        items,
+        [
+            serde_json::json!({"a": [1, 2, {"b": "}\"{"}], "c": null}),
+            serde_json::json!(["x", -1.5e3]),
+            serde_json::json!("str"),
+        ]
+    );
+    assert_eq!(DESERIALIZED.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,57 @@
+use futures_lite::future::block_on;
+use futures_util::{io::Cursor, stream::TryStreamExt};
+use yz_futures_codec::{
+    codec::{Limit, Lines, LinesCodec},
+    Framed,
+};
+use yz_futures_util::sink::SinkExt;
+
+#[test]
+fn halves_work_independently() {
+    let cur = Cursor::new(b"Hello\nWorld\n".to_vec());
+    let (mut read, mut write) = Framed::new(cur, Lines).split();
+
+    let next = block_on(read.try_next()).unwrap().unwrap();
+    assert_eq!(next, "Hello\n");
+    block_on(write.send_unpin("Bye\n")).unwrap();
+    let next = block_on(read.try_next()).unwrap().unwrap();
+    assert_eq!(next, "World\n");
+
+    let framed = read.reunite(write).unwrap();
+    assert_eq!(framed.get_ref(), b"Hello\nWorld\nBye\n");
+}
+
+#[test]
+fn reunite_keeps_buffers() {
+    let cur = Cursor::new(b"one\ntwo\n".to_vec());
+    let (mut read, write) = Framed::new(cur, Lines).split();
+    let next = block_on(read.try_next()).unwrap().unwrap();
+    assert_eq!(next, "one\n");
+    assert_eq!(&read.read_buffer()[..], b"two\n");
+
+    let mut framed = write.reunite(read).unwrap();
+    assert_eq!(&framed.read_buffer()[..], b"two\n");
+    let next = block_on(framed.try_next()).unwrap().unwrap();
+    assert_eq!(next, "two\n");
+}
+
+#[test]
+fn reunite_rejects_foreign_halves() {
+    let (read, _) = Framed::new(Cursor::new(Vec::<u8>::new()), Lines).split();
+    let (_, write) = Framed::new(Cursor::new(Vec::<u8>::new()), Lines).split();
+    assert!(read.reunite(write).is_err());
+}
+
+#[test]
+fn halves_have_own_codecs() {
+    let cur = Cursor::new(b"one\n".to_vec());
+    let (mut read, mut write) = Framed::new(cur, Limit::new(LinesCodec::new(), 16)).split();
+    read.codec = Limit::new(LinesCodec::new().with_strip_terminator(true), 16);
+
+    let next = block_on(read.try_next()).unwrap().unwrap();
+    assert_eq!(next, "one");
+    block_on(write.send_unpin("two\n")).unwrap();
+
+    let framed = read.reunite(write).unwrap();
+    assert_eq!(framed.get_ref(), b"one\ntwo\n");
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "stats")]
+
+use futures_lite::future::block_on;
+use futures_util::{io::Cursor, stream::TryStreamExt};
+use yz_futures_codec::{codec::Lines, Framed, Stats};
+use yz_futures_util::sink::SinkExt;
+
+#[test]
+fn counts_traffic() {
+    let cur = Cursor::new(b"a\nbb\n".to_vec());
+    let mut framed = Framed::new(cur, Lines);
+    assert_eq!(framed.stats(), &Stats::default());
+
+    while block_on(framed.try_next()).unwrap().is_some() {}
+    block_on(framed.send_unpin("xyz\n")).unwrap();
+    block_on(framed.send_unpin("0123456789\n")).unwrap();
+
+    assert_eq!(
+        framed.stats(),
+        &Stats {
+            frames_decoded: 2,
+            frames_encoded: 2,
+            bytes_read: 5,
+            bytes_written: 15,
+            flushes: 2,
+            peak_read_buffer: 5,
+            peak_write_buffer: 11,
+        }
+    );
+}
+
+#[test]
+fn split_keeps_stats() {
+    let cur = Cursor::new(b"a\n".to_vec());
+    let (mut read, mut write) = Framed::new(cur, Lines).split();
+    block_on(read.try_next()).unwrap().unwrap();
+    block_on(write.send_unpin("xyz\n")).unwrap();
+
+    let framed = read.reunite(write).unwrap();
+    let stats = framed.stats();
+    assert_eq!(stats.frames_decoded, 1);
+    assert_eq!(stats.bytes_read, 2);
+    assert_eq!(stats.frames_encoded, 1);
+    assert_eq!(stats.bytes_written, 4);
+}
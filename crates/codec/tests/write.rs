@@ -4,7 +4,8 @@ use futures_util::io::{AsyncWrite, Cursor};
 use futures_util::stream;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use yz_futures_codec::{codec::BytesCodec, codec::Lines, Framed};
+use yz_futures_codec::codec::{BytesCodec, Length, Limit, LimitError, Lines};
+use yz_futures_codec::{Bytes, Error, Framed, FramedWrite};
 use yz_futures_util::sink::SinkExt;
 
 // An AsyncWrite which is always ready and just consumes the data
@@ -76,3 +77,94 @@ fn send_high_water_mark() {
     assert_eq!(io.num_poll_write, 2);
     assert_eq!(io.last_write_size, 499);
 }
+
+#[test]
+fn framed_write_high_water_mark() {
+    let mut stream = stream::iter((0..999).map(|_| b"\0").map(Ok));
+
+    let io = AsyncWriteNull {
+        num_poll_write: 0,
+        last_write_size: 0,
+    };
+
+    let mut framer = FramedWrite::new(io, BytesCodec {});
+    framer.w_high_water_mark = 500;
+    block_on(framer.send_all_unpin(&mut stream)).unwrap();
+    assert!(framer.write_buffer().is_empty());
+    let io = framer.into_inner();
+    assert_eq!(io.num_poll_write, 2);
+    assert_eq!(io.last_write_size, 499);
+}
+
+// An AsyncWrite which records the chunks passed to poll_write_vectored
+#[derive(Default)]
+struct AsyncWriteVectored {
+    written: Vec<u8>,
+    slices: Vec<(*const u8, usize)>,
+}
+impl AsyncWrite for AsyncWriteVectored {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.poll_write_vectored(cx, &[std::io::IoSlice::new(buf)])
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        let mut n = 0;
+        for buf in bufs {
+            self.slices.push((buf.as_ptr(), buf.len()));
+            self.written.extend_from_slice(buf);
+            n += buf.len();
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn large_bytes_are_not_copied() {
+    let payload = Bytes::from(vec![0x55u8; 64 * 1024]);
+    let mut framer = FramedWrite::new(AsyncWriteVectored::default(), Length::<u64>::new());
+    block_on(framer.send_unpin(&payload)).unwrap();
+    block_on(framer.send_unpin("small")).unwrap();
+    let io = framer.into_inner();
+
+    assert_eq!(
+        io.slices,
+        [
+            (io.slices[0].0, 8),
+            (payload.as_ptr(), payload.len()),
+            (io.slices[2].0, 8 + 5)
+        ]
+    );
+    assert_eq!(&io.written[..8], &[0, 0, 0, 0, 0, 1, 0, 0]);
+    assert_eq!(&io.written[8..8 + payload.len()], &payload[..]);
+    assert_eq!(&io.written[8 + payload.len()..], b"\0\0\0\0\0\0\0\x05small");
+}
+
+#[test]
+fn limit_discards_oversized_shared_frame() {
+    let payload = Bytes::from(vec![0x55u8; 64 * 1024]);
+    let mut framer = FramedWrite::new(
+        AsyncWriteVectored::default(),
+        Limit::new(Length::<u32>::new(), 1024),
+    );
+    match block_on(framer.send_unpin(&payload)) {
+        Err(Error::Codec(LimitError::LimitExceeded(x))) => assert_eq!(x, 4 + payload.len()),
+        x => panic!("unexpected result: {:?}", x),
+    }
+    assert!(framer.write_buffer().is_empty());
+}